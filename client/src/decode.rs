@@ -0,0 +1,200 @@
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use shrimp::instructions::*;
+use shrimp::state::*;
+
+// ──────────────────────────── Accounts ───────────────────────────
+
+/// Decode raw account data (including the 8 byte discriminator) into `T`
+pub fn decode_account<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+macro_rules! shrimp_accounts {
+    ($($name:ident),* $(,)?) => {
+        /// Any account owned by the Shrimp Farm program
+        #[allow(clippy::large_enum_variant)]
+        pub enum ShrimpAccount {
+            $($name($name),)*
+        }
+
+        impl ShrimpAccount {
+            /// Decode account data by matching its discriminator against every program account type
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                let discriminator = data.get(..8)?;
+                $(
+                    if discriminator == $name::DISCRIMINATOR {
+                        return decode_account::<$name>(data).ok().map(Self::$name);
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+shrimp_accounts!(
+    GameState,
+    PlayerState,
+    ReferralStats,
+    LockState,
+    GameRegistry,
+    UsernameToAddress,
+    PlayerProfile,
+    UsernamePolicy,
+    ReservedName,
+    MinterState,
+);
+
+// ────────────────────────── Return data ──────────────────────────
+
+/// Decode the return data of a quote instruction into `BuyQuote`, `SellQuote` or `HatchQuote`
+pub fn decode_return_data<T: AnchorDeserialize>(data: &[u8]) -> std::io::Result<T> {
+    T::try_from_slice(data)
+}
+
+// ───────────────────────────── Events ────────────────────────────
+
+/// Prefix of the log line `emit!` writes event data to
+const PROGRAM_DATA: &str = "Program data: ";
+
+macro_rules! shrimp_events {
+    ($($name:ident),* $(,)?) => {
+        /// Any event emitted by the Shrimp Farm program
+        pub enum ShrimpEvent {
+            $($name($name),)*
+        }
+
+        impl ShrimpEvent {
+            /// Decode event data (discriminator followed by the Borsh encoded event)
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                let discriminator = data.get(..8)?;
+                $(
+                    if discriminator == $name::DISCRIMINATOR {
+                        return $name::deserialize(&mut &data[8..]).ok().map(Self::$name);
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+shrimp_events!(
+    Initialized,
+    NFTCollectionSet,
+    DevWithdrawn,
+    UserRegistered,
+    UserWithdrawn,
+    Buy,
+    PreMarketBuy,
+    ReferralCredited,
+    ReferralExpired,
+    Sell,
+    Hatch,
+    MarketUpdated,
+    NftMinted,
+    AdminMinted,
+    MinterSet,
+    TestnetBonusEvent,
+    FeeScheduleSet,
+    GameOver,
+    ClaimPolicySet,
+    UnclaimedSwept,
+    SeasonStarted,
+    PlayerCarriedOver,
+    UsernameChanged,
+    UsernameReleased,
+    UsernameFeeSet,
+    UsernamePolicySet,
+    ReservedNameSet,
+    ReferrerSet,
+    ReferrerPolicySet,
+    ReferralSplitSet,
+    ReferralTiersSet,
+    ReferralGuardsSet,
+    ReferralWindowSet,
+);
+
+/// Collect every Shrimp Farm event from a transaction's log messages.
+///
+/// Log lines that are not `Program data:` entries, or whose payload is not a
+/// known event, are skipped.
+pub fn parse_events<'a>(logs: impl IntoIterator<Item = &'a str>) -> Vec<ShrimpEvent> {
+    logs.into_iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|data| ShrimpEvent::decode(&data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::{AccountSerialize, AnchorSerialize, Event};
+
+    fn account_data<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = vec![];
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn accounts_round_trip() {
+        let game = GameState { authority: Pubkey::new_unique(), market_eggs: 123, referral_tiers: [2, 1], ..Default::default() };
+        let data = account_data(&game);
+        let decoded = decode_account::<GameState>(&data).unwrap();
+        assert_eq!((decoded.authority, decoded.market_eggs, decoded.referral_tiers), (game.authority, 123, [2, 1]));
+        let Some(ShrimpAccount::GameState(decoded)) = ShrimpAccount::decode(&data) else { panic!("not a game state") };
+        assert_eq!(decoded.authority, game.authority);
+
+        let stats = ReferralStats { referrer: Pubkey::new_unique(), referees: 3, ..Default::default() };
+        let Some(ShrimpAccount::ReferralStats(decoded)) = ShrimpAccount::decode(&account_data(&stats)) else { panic!("not referral stats") };
+        assert_eq!((decoded.referrer, decoded.referees), (stats.referrer, 3));
+
+        // The discriminator picks the type, so other accounts do not decode as a game state
+        assert!(decode_account::<GameState>(&account_data(&stats)).is_err());
+        assert!(ShrimpAccount::decode(&[0; 8]).is_none());
+    }
+
+    #[test]
+    fn quotes_round_trip_through_return_data() {
+        let buy = BuyQuote { game_balance: 1, eggs: 2, shrimp: 3, dev_fee: 4, premarket_fee: 5 };
+        assert_eq!(decode_return_data::<BuyQuote>(&buy.try_to_vec().unwrap()).unwrap(), buy);
+
+        let sell = SellQuote {
+            game_balance: 1,
+            eggs: 2,
+            eggs_forfeited: 3,
+            bonus_percent: 4,
+            sol_amount: 5,
+            dev_fee: 6,
+            premarket_fee: 7,
+            sol_received: 8,
+            ends_game: true,
+        };
+        assert_eq!(decode_return_data::<SellQuote>(&sell.try_to_vec().unwrap()).unwrap(), sell);
+
+        let hatch = HatchQuote { eggs: 1, bonus_percent: 2, shrimp: 3 };
+        assert_eq!(decode_return_data::<HatchQuote>(&hatch.try_to_vec().unwrap()).unwrap(), hatch);
+        assert!(decode_return_data::<SellQuote>(&hatch.try_to_vec().unwrap()).is_err());
+    }
+
+    #[test]
+    fn events_are_parsed_from_program_data_logs() {
+        let event = ReferralTiersSet { event_index: 7, tiers: [2, 1], timestamp: 1_700_000_000 };
+        let data = format!("{}{}", PROGRAM_DATA, STANDARD.encode(event.data()));
+        let logs = [
+            "Program log: Instruction: SetReferralTiers",
+            data.as_str(),
+            "Program data: not base64",
+            "Program data: AAAAAAAAAAA=",
+        ];
+
+        let events = parse_events(logs);
+        assert_eq!(events.len(), 1);
+        let ShrimpEvent::ReferralTiersSet(parsed) = &events[0] else { panic!("not a ReferralTiersSet event") };
+        assert_eq!((parsed.event_index, parsed.tiers, parsed.timestamp), (7, [2, 1], 1_700_000_000));
+    }
+}
@@ -0,0 +1,867 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use shrimp::{accounts, instruction};
+
+use crate::pda::*;
+use crate::{CurveParams, FeeSchedule, PrizeMode, ReferralGuards, ReferrerPolicy, SeasonCarryOver, SweepDestination, UsernameRules, CANDY_MACHINE_PROGRAM_ID, MPL_CORE_PROGRAM_ID};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: shrimp::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// ───────────────────────────── Setup ─────────────────────────────
+
+/// `devs` are `[dev1, dev2, dev3]` and must be distinct; `fees`, `curve` and
+/// `prize_mode` of `None` use the default fee schedule, curve and premarket prize
+#[allow(clippy::too_many_arguments)]
+pub fn initialize(
+    owner: &Pubkey,
+    authority: &Pubkey,
+    devs: [Pubkey; 3],
+    premarket_end: u64,
+    cooldown: u64,
+    test_env: bool,
+    fees: Option<FeeSchedule>,
+    curve: Option<CurveParams>,
+    prize_mode: Option<PrizeMode>,
+) -> Instruction {
+    build(
+        accounts::Initialize {
+            owner: *owner,
+            authority: *authority,
+            lock_state: find_lock_state().0,
+            game_registry: find_game_registry().0,
+            username_policy: find_username_policy().0,
+            game_state: find_game_state(authority).0,
+            dev_vault: find_dev_vault(authority).0,
+            premarket_vault: find_premarket_vault(authority).0,
+            payables_vault: find_payables_vault(authority).0,
+            pool_vault: find_pool_vault(authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::Initialize {
+            dev1: devs[0],
+            dev2: devs[1],
+            dev3: devs[2],
+            premarket_end,
+            cooldown,
+            test_env,
+            fees,
+            curve,
+            prize_mode,
+        },
+    )
+}
+
+/// `owner` must be the registry owner and `previous_authority` the authority of
+/// the current season, which must be over. Devs and `test_env` carry over from it.
+#[allow(clippy::too_many_arguments)]
+pub fn start_season(
+    owner: &Pubkey,
+    authority: &Pubkey,
+    previous_authority: &Pubkey,
+    premarket_end: u64,
+    cooldown: u64,
+    fees: Option<FeeSchedule>,
+    curve: Option<CurveParams>,
+    prize_mode: Option<PrizeMode>,
+    carry_over: SeasonCarryOver,
+) -> Instruction {
+    build(
+        accounts::StartSeason {
+            owner: *owner,
+            authority: *authority,
+            game_registry: find_game_registry().0,
+            previous_authority: *previous_authority,
+            previous_game_state: find_game_state(previous_authority).0,
+            game_state: find_game_state(authority).0,
+            dev_vault: find_dev_vault(authority).0,
+            premarket_vault: find_premarket_vault(authority).0,
+            payables_vault: find_payables_vault(authority).0,
+            pool_vault: find_pool_vault(authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::StartSeason {
+            premarket_end,
+            cooldown,
+            fees,
+            curve,
+            prize_mode,
+            carry_over,
+        },
+    )
+}
+
+pub fn set_collection(
+    authority: &Pubkey,
+    candy_machine: &Pubkey,
+    candy_machine_authority: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::SetCollection {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            candy_machine: *candy_machine,
+            nft_mint_authority: find_nft_mint_authority(authority).0,
+            candy_machine_authority: *candy_machine_authority,
+            candy_machine_program: CANDY_MACHINE_PROGRAM_ID,
+        },
+        instruction::SetCollection {},
+    )
+}
+
+pub fn set_program_guards(
+    authority: &Pubkey,
+    max_ixs: u8,
+    program_whitelist: Vec<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::SetProgramGuards {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetProgramGuards {
+            max_ixs,
+            program_whitelist,
+        },
+    )
+}
+
+/// Only allowed before the premarket ends
+pub fn set_fee_schedule(authority: &Pubkey, fees: FeeSchedule) -> Instruction {
+    build(
+        accounts::SetFeeSchedule {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetFeeSchedule { fees },
+    )
+}
+
+/// `owner` must be the registry owner
+pub fn set_username_policy(owner: &Pubkey, rules: UsernameRules) -> Instruction {
+    build(
+        accounts::SetUsernamePolicy {
+            owner: *owner,
+            game_registry: find_game_registry().0,
+            username_policy: find_username_policy().0,
+        },
+        instruction::SetUsernamePolicy { rules },
+    )
+}
+
+/// `owner` must be the registry owner
+pub fn reserve_username(owner: &Pubkey, username: &str) -> Instruction {
+    build(
+        accounts::ReserveUsername {
+            owner: *owner,
+            game_registry: find_game_registry().0,
+            reserved_name: find_reserved_name(username).0,
+            system_program: system_program::ID,
+        },
+        instruction::ReserveUsername {
+            username: username.to_string(),
+        },
+    )
+}
+
+/// `owner` must be the registry owner
+pub fn unreserve_username(owner: &Pubkey, username: &str) -> Instruction {
+    build(
+        accounts::UnreserveUsername {
+            owner: *owner,
+            game_registry: find_game_registry().0,
+            reserved_name: find_reserved_name(username).0,
+        },
+        instruction::UnreserveUsername {
+            username: username.to_string(),
+        },
+    )
+}
+
+pub fn set_username_fee(authority: &Pubkey, fee: u64) -> Instruction {
+    build(
+        accounts::SetUsernameFee {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetUsernameFee { fee },
+    )
+}
+
+pub fn set_referrer_policy(authority: &Pubkey, policy: ReferrerPolicy) -> Instruction {
+    build(
+        accounts::SetReferrerPolicy {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetReferrerPolicy { policy },
+    )
+}
+
+/// Only before the premarket ends; `tiers` are the % of referred buys paid to
+/// the referrer's referrer and theirs, together at most the game's referral fee
+pub fn set_referral_tiers(authority: &Pubkey, tiers: [u64; 2]) -> Instruction {
+    build(
+        accounts::SetReferralTiers {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetReferralTiers { tiers },
+    )
+}
+
+/// Only before the premarket ends; a `referee_cap` of 0 leaves referral income uncapped
+pub fn set_referral_guards(authority: &Pubkey, guards: ReferralGuards) -> Instruction {
+    build(
+        accounts::SetReferralGuards {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetReferralGuards { guards },
+    )
+}
+
+/// Only before the premarket ends; `referral_window` is in seconds from a player's
+/// first referred buy, up to a year (and at least 7 days outside test environments)
+pub fn set_referral_window(authority: &Pubkey, referral_window: u64) -> Instruction {
+    build(
+        accounts::SetReferralWindow {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetReferralWindow { referral_window },
+    )
+}
+
+/// Only allowed before the game is over
+pub fn set_claim_policy(authority: &Pubkey, claim_window: u64, destination: SweepDestination) -> Instruction {
+    build(
+        accounts::SetClaimPolicy {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::SetClaimPolicy { claim_window, destination },
+    )
+}
+
+pub fn set_minter(authority: &Pubkey, minter: &Pubkey) -> Instruction {
+    build(
+        accounts::SetMinter {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            minter_state: find_minter_state(authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::SetMinter { minter: *minter },
+    )
+}
+
+// ───────────────────────────── Trading ───────────────────────────
+
+/// Accounts shared by `buy_shrimp` and `buy_premarket`.
+///
+/// `referrer` is optional; when supplied its `PlayerState` (which must already
+/// exist) and `ReferralStats` PDAs are derived and passed alongside it. Once the
+/// player has a referrer it is required and must be their `current_referrer`
+/// (changes go through `set_referrer`).
+///
+/// `upline` holds the referrer's own referrer and theirs, as far as they exist,
+/// to pay the game's second and third referral tiers. The buy fails when a tier
+/// the game pays is left out, unless it is the player or the referrer.
+fn buy_accounts(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer: Option<&Pubkey>,
+    upline: &[Pubkey],
+) -> accounts::BuyAccounts {
+    accounts::BuyAccounts {
+        payer: *payer,
+        player: *player,
+        authority: *authority,
+        game_state: find_game_state(authority).0,
+        dev_vault: find_dev_vault(authority).0,
+        premarket_vault: find_premarket_vault(authority).0,
+        payables_vault: find_payables_vault(authority).0,
+        pool_vault: find_pool_vault(authority).0,
+        player_state: find_player_state(player, authority).0,
+        referrer_state: referrer.map(|r| find_player_state(r, authority).0),
+        referrer_stats: referrer.map(|r| find_referral_stats(r, authority).0),
+        referrer: referrer.copied(),
+        referrer_profile: referrer.map(|r| find_profile(r).0),
+        second_tier_state: upline.first().map(|r| find_player_state(r, authority).0),
+        third_tier_state: upline.get(1).map(|r| find_player_state(r, authority).0),
+        sysvar_instructions: sysvar::instructions::ID,
+        system_program: system_program::ID,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn buy_shrimp(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer: Option<&Pubkey>,
+    upline: &[Pubkey],
+    amount: u64,
+    min_shrimp_out: Option<u128>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        buy_accounts(payer, player, authority, referrer, upline),
+        instruction::BuyShrimp {
+            amount,
+            min_shrimp_out,
+            valid_until,
+        },
+    )
+}
+
+pub fn buy_premarket(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer: Option<&Pubkey>,
+    upline: &[Pubkey],
+    amount: u64,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        buy_accounts(payer, player, authority, referrer, upline),
+        instruction::BuyPremarket {
+            amount,
+            valid_until,
+        },
+    )
+}
+
+/// Accounts shared by `buy_shrimp_by_username` and `buy_premarket_by_username`.
+///
+/// `referrer` is the address `referrer_username` resolves to, used to derive
+/// the referrer's state and profile; `upline` is as for `buy_shrimp`.
+fn buy_by_username_accounts(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer_username: &str,
+    referrer: &Pubkey,
+    upline: &[Pubkey],
+) -> accounts::BuyByUsernameAccounts {
+    accounts::BuyByUsernameAccounts {
+        payer: *payer,
+        player: *player,
+        authority: *authority,
+        game_state: find_game_state(authority).0,
+        dev_vault: find_dev_vault(authority).0,
+        premarket_vault: find_premarket_vault(authority).0,
+        payables_vault: find_payables_vault(authority).0,
+        pool_vault: find_pool_vault(authority).0,
+        player_state: find_player_state(player, authority).0,
+        referrer_username: find_username_to_address(referrer_username).0,
+        referrer_state: find_player_state(referrer, authority).0,
+        referrer_stats: find_referral_stats(referrer, authority).0,
+        referrer_profile: find_profile(referrer).0,
+        second_tier_state: upline.first().map(|r| find_player_state(r, authority).0),
+        third_tier_state: upline.get(1).map(|r| find_player_state(r, authority).0),
+        sysvar_instructions: sysvar::instructions::ID,
+        system_program: system_program::ID,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn buy_shrimp_by_username(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer_username: &str,
+    referrer: &Pubkey,
+    upline: &[Pubkey],
+    amount: u64,
+    min_shrimp_out: Option<u128>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        buy_by_username_accounts(payer, player, authority, referrer_username, referrer, upline),
+        instruction::BuyShrimpByUsername {
+            username: referrer_username.to_string(),
+            amount,
+            min_shrimp_out,
+            valid_until,
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn buy_premarket_by_username(
+    payer: &Pubkey,
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer_username: &str,
+    referrer: &Pubkey,
+    upline: &[Pubkey],
+    amount: u64,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        buy_by_username_accounts(payer, player, authority, referrer_username, referrer, upline),
+        instruction::BuyPremarketByUsername {
+            username: referrer_username.to_string(),
+            amount,
+            valid_until,
+        },
+    )
+}
+
+/// Accounts shared by the sell and hatch instructions.
+///
+/// `nft_asset` is an optional collection NFT owned by the player, used for the bonus.
+fn sell_and_hatch_accounts(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+) -> accounts::SellAndHatchAccounts {
+    accounts::SellAndHatchAccounts {
+        player: *player,
+        authority: *authority,
+        game_state: find_game_state(authority).0,
+        dev_vault: find_dev_vault(authority).0,
+        premarket_vault: find_premarket_vault(authority).0,
+        payables_vault: find_payables_vault(authority).0,
+        pool_vault: find_pool_vault(authority).0,
+        player_state: find_player_state(player, authority).0,
+        nft_asset: nft_asset.copied(),
+        sysvar_instructions: sysvar::instructions::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn sell_eggs(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+    min_lamports_out: Option<u64>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        sell_and_hatch_accounts(player, authority, nft_asset),
+        instruction::SellEggs {
+            min_lamports_out,
+            valid_until,
+        },
+    )
+}
+
+pub fn hatch_eggs(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        sell_and_hatch_accounts(player, authority, nft_asset),
+        instruction::HatchEggs { valid_until },
+    )
+}
+
+/// Sells `eggs` of the player's eggs (before bonuses) and keeps the rest
+pub fn sell_eggs_partial(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+    eggs: u128,
+    min_lamports_out: Option<u64>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        sell_and_hatch_accounts(player, authority, nft_asset),
+        instruction::SellEggsPartial {
+            eggs,
+            min_lamports_out,
+            valid_until,
+        },
+    )
+}
+
+/// Sells `sell_bps` (out of 10000) of the player's eggs and hatches the rest
+pub fn hatch_and_sell(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+    sell_bps: u16,
+    min_lamports_out: Option<u64>,
+    valid_until: Option<u64>,
+) -> Instruction {
+    build(
+        sell_and_hatch_accounts(player, authority, nft_asset),
+        instruction::HatchAndSell {
+            sell_bps,
+            min_lamports_out,
+            valid_until,
+        },
+    )
+}
+
+/// Permissionless; any `caller` can end a game whose market reached the endgame limit
+pub fn finalize_game(caller: &Pubkey, authority: &Pubkey) -> Instruction {
+    build(
+        accounts::FinalizeGame {
+            caller: *caller,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::FinalizeGame {},
+    )
+}
+
+// ───────────────────────────── Quotes ────────────────────────────
+// Read-only; simulate and decode the return data with `decode_return_data`
+
+pub fn quote_buy(authority: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::QuoteBuy {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::QuoteBuy { amount },
+    )
+}
+
+fn quote_eggs_accounts(
+    player: &Pubkey,
+    authority: &Pubkey,
+    nft_asset: Option<&Pubkey>,
+) -> accounts::QuoteEggsAccounts {
+    accounts::QuoteEggsAccounts {
+        player: *player,
+        authority: *authority,
+        game_state: find_game_state(authority).0,
+        player_state: find_player_state(player, authority).0,
+        nft_asset: nft_asset.copied(),
+    }
+}
+
+pub fn quote_sell(player: &Pubkey, authority: &Pubkey, nft_asset: Option<&Pubkey>) -> Instruction {
+    build(
+        quote_eggs_accounts(player, authority, nft_asset),
+        instruction::QuoteSell {},
+    )
+}
+
+pub fn quote_hatch(player: &Pubkey, authority: &Pubkey, nft_asset: Option<&Pubkey>) -> Instruction {
+    build(
+        quote_eggs_accounts(player, authority, nft_asset),
+        instruction::QuoteHatch {},
+    )
+}
+
+// ─────────────────────────── Withdrawals ─────────────────────────
+
+pub fn user_withdraw(player: &Pubkey, authority: &Pubkey) -> Instruction {
+    build(
+        accounts::UserWithdraw {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            premarket_vault: find_premarket_vault(authority).0,
+            payables_vault: find_payables_vault(authority).0,
+            pool_vault: find_pool_vault(authority).0,
+            player_state: find_player_state(player, authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::UserWithdraw {},
+    )
+}
+
+/// `signer` must be the authority or one of the devs; `devs` are `[dev1, dev2, dev3]`
+/// as stored in `GameState`.
+pub fn dev_withdraw(signer: &Pubkey, authority: &Pubkey, devs: [Pubkey; 3]) -> Instruction {
+    build(
+        accounts::DevWithdraw {
+            signer: *signer,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            dev_vault: find_dev_vault(authority).0,
+            dev1: devs[0],
+            dev2: devs[1],
+            dev3: devs[2],
+            system_program: system_program::ID,
+        },
+        instruction::DevWithdraw {},
+    )
+}
+
+/// Permissionless once the claim window has passed; `next_authority` is required
+/// when the game sweeps into the next season's pool.
+pub fn sweep_unclaimed(caller: &Pubkey, authority: &Pubkey, next_authority: Option<&Pubkey>) -> Instruction {
+    build(
+        accounts::SweepUnclaimed {
+            caller: *caller,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            dev_vault: find_dev_vault(authority).0,
+            premarket_vault: find_premarket_vault(authority).0,
+            payables_vault: find_payables_vault(authority).0,
+            pool_vault: find_pool_vault(authority).0,
+            next_authority: next_authority.copied(),
+            next_game_state: next_authority.map(|next| find_game_state(next).0),
+            next_pool_vault: next_authority.map(|next| find_pool_vault(next).0),
+            system_program: system_program::ID,
+        },
+        instruction::SweepUnclaimed {},
+    )
+}
+
+// ───────────────────────────── Players ───────────────────────────
+
+pub fn register(player: &Pubkey, authority: &Pubkey, username: &str) -> Instruction {
+    build(
+        accounts::Register {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            player_state: find_player_state(player, authority).0,
+            profile: find_profile(player).0,
+            username_policy: find_username_policy().0,
+            reserved_name: find_reserved_name(username).0,
+            username_to_address_account: find_username_to_address(username).0,
+            sysvar_instructions: sysvar::instructions::ID,
+            system_program: system_program::ID,
+        },
+        instruction::Register {
+            username: username.to_string(),
+        },
+    )
+}
+
+/// `current_username` is the username held in the player's profile; the game
+/// run by `authority` receives the change fee
+pub fn change_username(player: &Pubkey, authority: &Pubkey, current_username: &str, username: &str) -> Instruction {
+    build(
+        accounts::ChangeUsername {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            pool_vault: find_pool_vault(authority).0,
+            profile: find_profile(player).0,
+            old_username_to_address_account: find_username_to_address(current_username).0,
+            username_policy: find_username_policy().0,
+            reserved_name: find_reserved_name(username).0,
+            username_to_address_account: find_username_to_address(username).0,
+            system_program: system_program::ID,
+        },
+        instruction::ChangeUsername {
+            username: username.to_string(),
+        },
+    )
+}
+
+/// `username` is the username held in the player's profile
+pub fn release_username(player: &Pubkey, username: &str) -> Instruction {
+    build(
+        accounts::ReleaseUsername {
+            player: *player,
+            profile: find_profile(player).0,
+            username_to_address_account: find_username_to_address(username).0,
+        },
+        instruction::ReleaseUsername {},
+    )
+}
+
+/// Brings the player's bonuses over from the season run by `previous_authority`
+pub fn carry_over_player(player: &Pubkey, authority: &Pubkey, previous_authority: &Pubkey) -> Instruction {
+    build(
+        accounts::CarryOverPlayer {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            previous_authority: *previous_authority,
+            previous_player_state: find_player_state(player, previous_authority).0,
+            player_state: find_player_state(player, authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::CarryOverPlayer {},
+    )
+}
+
+/// Replaces the player's referrer as the game's `ReferrerPolicy` allows.
+/// `previous_referrer` is the player's current referrer, if they have one.
+pub fn set_referrer(
+    player: &Pubkey,
+    authority: &Pubkey,
+    referrer: &Pubkey,
+    previous_referrer: Option<&Pubkey>,
+) -> Instruction {
+    build(
+        accounts::SetReferrer {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            player_state: find_player_state(player, authority).0,
+            referrer: *referrer,
+            referrer_stats: find_referral_stats(referrer, authority).0,
+            previous_referrer_stats: previous_referrer.map(|r| find_referral_stats(r, authority).0),
+            referrer_profile: find_profile(referrer).0,
+            system_program: system_program::ID,
+        },
+        instruction::SetReferrer {},
+    )
+}
+
+/// `cashback` is the % of referred buys given back to the referees, between the
+/// game's cashback and the whole referral budget
+pub fn set_referral_split(player: &Pubkey, authority: &Pubkey, cashback: u64) -> Instruction {
+    build(
+        accounts::SetReferralSplit {
+            player: *player,
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+            player_state: find_player_state(player, authority).0,
+        },
+        instruction::SetReferralSplit { cashback },
+    )
+}
+
+// ──────────────────────────── NFT mints ──────────────────────────
+
+/// `asset` is a fresh keypair that must also sign the transaction
+pub fn mint_nft(
+    player: &Pubkey,
+    authority: &Pubkey,
+    candy_machine: &Pubkey,
+    collection: &Pubkey,
+    asset: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::MintNft {
+            player: *player,
+            authority: *authority,
+            player_state: find_player_state(player, authority).0,
+            game_state: find_game_state(authority).0,
+            candy_machine_program: CANDY_MACHINE_PROGRAM_ID,
+            candy_machine: *candy_machine,
+            authority_pda: find_candy_machine_authority_pda(candy_machine).0,
+            mint_authority: find_nft_mint_authority(authority).0,
+            asset: *asset,
+            collection: *collection,
+            mpl_core_program: MPL_CORE_PROGRAM_ID,
+            system_program: system_program::ID,
+            sysvar_instructions: sysvar::instructions::ID,
+            recent_slothashes: sysvar::slot_hashes::ID,
+        },
+        instruction::MintNft {},
+    )
+}
+
+/// Mints to `player` on behalf of the minter set with `set_minter`;
+/// `asset` is a fresh keypair that must also sign the transaction
+pub fn admin_mint(
+    admin: &Pubkey,
+    authority: &Pubkey,
+    player: &Pubkey,
+    candy_machine: &Pubkey,
+    collection: &Pubkey,
+    asset: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::AdminMint {
+            admin: *admin,
+            authority: *authority,
+            minter_state: find_minter_state(authority).0,
+            game_state: find_game_state(authority).0,
+            player: *player,
+            candy_machine_program: CANDY_MACHINE_PROGRAM_ID,
+            candy_machine: *candy_machine,
+            authority_pda: find_candy_machine_authority_pda(candy_machine).0,
+            mint_authority: find_nft_mint_authority(authority).0,
+            asset: *asset,
+            collection: *collection,
+            mpl_core_program: MPL_CORE_PROGRAM_ID,
+            system_program: system_program::ID,
+            sysvar_instructions: sysvar::instructions::ID,
+            recent_slothashes: sysvar::slot_hashes::ID,
+        },
+        instruction::AdminMint {},
+    )
+}
+
+// ──────────────────────── Test environment ───────────────────────
+
+pub fn set_market(authority: &Pubkey, market_eggs: u128) -> Instruction {
+    build(
+        accounts::SetMarket {
+            game_state: find_game_state(authority).0,
+            authority: *authority,
+        },
+        instruction::SetMarket { market_eggs },
+    )
+}
+
+pub fn end_premarket(authority: &Pubkey) -> Instruction {
+    build(
+        accounts::EndPremarket {
+            authority: *authority,
+            game_state: find_game_state(authority).0,
+        },
+        instruction::EndPremarket {},
+    )
+}
+
+pub fn testnet_bonus(authority: &Pubkey, player: &Pubkey) -> Instruction {
+    build(
+        accounts::TestnetBonus {
+            authority: *authority,
+            player: *player,
+            player_state: find_player_state(player, authority).0,
+            system_program: system_program::ID,
+        },
+        instruction::TestnetBonus {},
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    #[test]
+    fn buy_passes_the_referrer_and_upline_accounts() {
+        let (payer, authority, referrer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let upline = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let ix = buy_shrimp(&payer, &payer, &authority, Some(&referrer), &upline, 100, None, None);
+        assert_eq!(ix.program_id, shrimp::ID);
+        assert!(ix.data.starts_with(&instruction::BuyShrimp::DISCRIMINATOR));
+
+        // In `BuyAccounts` order
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(keys[..8], [
+            payer,
+            payer,
+            authority,
+            find_game_state(&authority).0,
+            find_dev_vault(&authority).0,
+            find_premarket_vault(&authority).0,
+            find_payables_vault(&authority).0,
+            find_pool_vault(&authority).0,
+        ]);
+        assert_eq!(keys[8..15], [
+            find_player_state(&payer, &authority).0,
+            find_player_state(&referrer, &authority).0,
+            find_referral_stats(&referrer, &authority).0,
+            find_player_state(&upline[0], &authority).0,
+            find_player_state(&upline[1], &authority).0,
+            referrer,
+            find_profile(&referrer).0,
+        ]);
+
+        // Left out optional accounts are passed as the program id
+        let ix = buy_shrimp(&payer, &payer, &authority, None, &[], 100, None, None);
+        assert!(ix.accounts[9..15].iter().all(|meta| meta.pubkey == shrimp::ID && !meta.is_writable));
+    }
+}
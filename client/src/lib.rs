@@ -0,0 +1,29 @@
+//! Rust client for the Shrimp Farm program.
+//!
+//! - `pda`: program address finders built from the on-chain seed constants
+//! - `instructions`: one builder per program instruction
+//! - `decode`: typed decoders for program accounts and emitted events
+
+pub mod decode;
+pub mod instructions;
+pub mod pda;
+
+pub use decode::*;
+pub use instructions::*;
+pub use pda::*;
+
+pub use shrimp::ID as PROGRAM_ID;
+pub use shrimp::state::{
+    BuyQuote, CurveParams, FeeSchedule, GameRegistry, GameState, HatchQuote, LockState,
+    MinterState, PlayerProfile, PlayerState, PrizeMode, ReferralGuards, ReferralStats,
+    ReferrerPolicy, ReservedName, SeasonCarryOver, SellQuote, SweepDestination, UsernamePolicy,
+    UsernameRules, UsernameToAddress,
+};
+
+use anchor_lang::prelude::{pubkey, Pubkey};
+
+/// Metaplex Core Candy Machine program used for NFT mints
+pub const CANDY_MACHINE_PROGRAM_ID: Pubkey = pubkey!("CMACYFENjoBMHzapRXyo1JZkVS6EtaDDzkjMrmQLvr4J");
+
+/// Metaplex Core program that owns the NFT assets
+pub const MPL_CORE_PROGRAM_ID: Pubkey = pubkey!("CoREENxT6tW1HoK8ypY1SxRMZTcVPm7R94rH4PZNhX7d");
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::Pubkey;
+use shrimp::state::*;
+
+use crate::CANDY_MACHINE_PROGRAM_ID;
+
+// Game accounts
+
+pub fn find_game_state(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GameState::SEED, authority.as_ref()], &shrimp::ID)
+}
+
+pub fn find_player_state(player: &Pubkey, authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[player.as_ref(), PlayerState::SEED, authority.as_ref()],
+        &shrimp::ID,
+    )
+}
+
+pub fn find_referral_stats(referrer: &Pubkey, authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ReferralStats::SEED, referrer.as_ref(), authority.as_ref()],
+        &shrimp::ID,
+    )
+}
+
+pub fn find_lock_state() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LockState::SEED], &shrimp::ID)
+}
+
+pub fn find_game_registry() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GameRegistry::SEED], &shrimp::ID)
+}
+
+// Vaults (system-owned PDAs holding each treasury bucket)
+
+pub fn find_dev_vault(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DEV_VAULT_SEED, authority.as_ref()], &shrimp::ID)
+}
+
+pub fn find_premarket_vault(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PREMARKET_VAULT_SEED, authority.as_ref()], &shrimp::ID)
+}
+
+pub fn find_payables_vault(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYABLES_VAULT_SEED, authority.as_ref()], &shrimp::ID)
+}
+
+pub fn find_pool_vault(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_VAULT_SEED, authority.as_ref()], &shrimp::ID)
+}
+
+// Usernames (global, not seeded with the authority)
+
+pub fn find_username_to_address(username: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UsernameToAddress::SEED, username.as_bytes()], &shrimp::ID)
+}
+
+pub fn find_profile(player: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PlayerProfile::SEED, player.as_ref()], &shrimp::ID)
+}
+
+pub fn find_username_policy() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UsernamePolicy::SEED], &shrimp::ID)
+}
+
+pub fn find_reserved_name(username: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ReservedName::SEED, username.as_bytes()], &shrimp::ID)
+}
+
+// NFT minting
+
+pub fn find_minter_state(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MinterState::SEED, authority.as_ref()], &shrimp::ID)
+}
+
+/// Program PDA set as the candy machine mint authority
+pub fn find_nft_mint_authority(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CANDY_MACHINE_AUTHORITY_SEED.as_bytes(), authority.as_ref()],
+        &shrimp::ID,
+    )
+}
+
+/// Candy machine's own authority PDA (owned by the candy machine program)
+pub fn find_candy_machine_authority_pda(candy_machine: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"candy_machine", candy_machine.as_ref()],
+        &CANDY_MACHINE_PROGRAM_ID,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seeds spelled out as the program's `#[account(seeds = …)]` constraints resolve them
+    fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &shrimp::ID)
+    }
+
+    #[test]
+    fn game_pdas_match_the_program_seeds() {
+        let authority = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        assert_eq!(find_game_state(&authority), pda(&[b"shrimp", authority.as_ref()]));
+        assert_eq!(find_player_state(&player, &authority), pda(&[player.as_ref(), b"shrimp", authority.as_ref()]));
+        assert_eq!(find_referral_stats(&player, &authority), pda(&[b"referral_stats", player.as_ref(), authority.as_ref()]));
+        assert_eq!(find_lock_state(), pda(&[b"shrimplock"]));
+        assert_eq!(find_game_registry(), pda(&[b"game_registry"]));
+
+        // Game and player states of the same key must not collide
+        assert_ne!(find_game_state(&authority).0, find_player_state(&authority, &authority).0);
+    }
+
+    #[test]
+    fn vault_pdas_match_the_program_seeds() {
+        let authority = Pubkey::new_unique();
+        assert_eq!(find_dev_vault(&authority), pda(&[b"dev_vault", authority.as_ref()]));
+        assert_eq!(find_premarket_vault(&authority), pda(&[b"premarket_vault", authority.as_ref()]));
+        assert_eq!(find_payables_vault(&authority), pda(&[b"payables_vault", authority.as_ref()]));
+        assert_eq!(find_pool_vault(&authority), pda(&[b"pool_vault", authority.as_ref()]));
+    }
+
+    #[test]
+    fn username_pdas_match_the_program_seeds() {
+        let player = Pubkey::new_unique();
+        assert_eq!(find_username_to_address("shrimpy"), pda(&[b"username_to_address", b"shrimpy"]));
+        assert_eq!(find_profile(&player), pda(&[b"profile", player.as_ref()]));
+        assert_eq!(find_username_policy(), pda(&[b"username_policy"]));
+        assert_eq!(find_reserved_name("admin"), pda(&[b"reserved_name", b"admin"]));
+    }
+
+    #[test]
+    fn minting_pdas_match_the_program_seeds() {
+        let authority = Pubkey::new_unique();
+        let candy_machine = Pubkey::new_unique();
+        assert_eq!(find_minter_state(&authority), pda(&[b"minter", authority.as_ref()]));
+        assert_eq!(find_nft_mint_authority(&authority), pda(&[b"candy_machine", authority.as_ref()]));
+        assert_eq!(
+            find_candy_machine_authority_pda(&candy_machine),
+            Pubkey::find_program_address(&[b"candy_machine", candy_machine.as_ref()], &CANDY_MACHINE_PROGRAM_ID)
+        );
+    }
+}
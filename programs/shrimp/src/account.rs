@@ -1,4 +1,7 @@
 use crate::state::*;
+use crate::error::CustomErrors;
+use crate::engine::{Referrer, Upline};
+use crate::helpers::{check_pda, is_registered};
 use anchor_lang::prelude::*;
 use mpl_core::accounts::BaseAssetV1;
 use crate::state::GameState;
@@ -24,6 +27,18 @@ pub struct BuyAccounts<'info> {
     )]
     pub game_state: Box<Account<'info, GameState>>,
 
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
     #[account(
         init_if_needed,
         payer = payer,
@@ -33,19 +48,130 @@ pub struct BuyAccounts<'info> {
     )]
     pub player_state: Box<Account<'info, PlayerState>>,
 
+    /// Must already exist, so a buy cannot create its own referrer
+    #[account(
+        mut,
+        seeds = [referrer.as_ref().map_or(player_state.current_referrer, |r| r.key()).as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub referrer_state: Option<Box<Account<'info, PlayerState>>>,
+
     #[account(
         init_if_needed,
         payer = payer,
-        space = 8 + PlayerState::INIT_SPACE,
-        seeds = [referrer.as_ref().unwrap().key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        space = 8 + ReferralStats::INIT_SPACE,
+        seeds = [ReferralStats::SEED, referrer.as_ref().map_or(player_state.current_referrer, |r| r.key()).as_ref(), authority.key().as_ref()],
         bump
     )]
-    pub referrer_state: Option<Box<Account<'info, PlayerState>>>,
+    pub referrer_stats: Option<Box<Account<'info, ReferralStats>>>,
+
+    /// Checked against the referrer's `current_referrer` in `parts`
+    #[account(mut)]
+    pub second_tier_state: Option<Box<Account<'info, PlayerState>>>,
+
+    /// Checked against the second tier's `current_referrer` in `parts`
+    #[account(mut)]
+    pub third_tier_state: Option<Box<Account<'info, PlayerState>>>,
 
-    /// CHECK: Can be any account
+    /// CHECK: Can be any account, the player's `current_referrer` when omitted
     #[account()]
     pub referrer: Option<AccountInfo<'info>>,
 
+    /// CHECK: Global profile of the referrer, which may not exist (checked with `is_registered`)
+    #[account(
+        seeds = [PlayerProfile::SEED, referrer.as_ref().map_or(player_state.current_referrer, |r| r.key()).as_ref()],
+        bump
+    )]
+    pub referrer_profile: Option<UncheckedAccount<'info>>,
+
+    /// Instructions sysvar account.
+    ///
+    /// CHECK: account constraints checked in account trait
+    #[account(address = sysvar::instructions::id())]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts shared by `buy_shrimp_by_username` and `buy_premarket_by_username`,
+/// where the referrer is resolved from their `UsernameToAddress` account
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct BuyByUsernameAccounts<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: No constraint required
+    #[account()]
+    pub player: AccountInfo<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PlayerState::INIT_SPACE,
+        seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub player_state: Box<Account<'info, PlayerState>>,
+
+    #[account(seeds = [UsernameToAddress::SEED, username.as_bytes()], bump)]
+    pub referrer_username: Account<'info, UsernameToAddress>,
+
+    /// Must already exist, so a buy cannot create its own referrer
+    #[account(
+        mut,
+        seeds = [referrer_username.address.as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub referrer_state: Box<Account<'info, PlayerState>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ReferralStats::INIT_SPACE,
+        seeds = [ReferralStats::SEED, referrer_username.address.as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub referrer_stats: Box<Account<'info, ReferralStats>>,
+
+    /// Checked against the referrer's `current_referrer` in `parts`
+    #[account(mut)]
+    pub second_tier_state: Option<Box<Account<'info, PlayerState>>>,
+
+    /// Checked against the second tier's `current_referrer` in `parts`
+    #[account(mut)]
+    pub third_tier_state: Option<Box<Account<'info, PlayerState>>>,
+
+    /// CHECK: Global profile of the referrer (checked with `is_registered`)
+    #[account(
+        seeds = [PlayerProfile::SEED, referrer_username.address.as_ref()],
+        bump
+    )]
+    pub referrer_profile: UncheckedAccount<'info>,
+
     /// Instructions sysvar account.
     ///
     /// CHECK: account constraints checked in account trait
@@ -55,6 +181,118 @@ pub struct BuyAccounts<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// The accounts a buy works on, borrowed from `BuyAccounts` or `BuyByUsernameAccounts`
+pub struct BuyParts<'a, 'info> {
+    pub payer: &'a Signer<'info>,
+    pub player: &'a AccountInfo<'info>,
+    pub game_state: &'a mut Account<'info, GameState>,
+    pub dev_vault: &'a SystemAccount<'info>,
+    pub premarket_vault: &'a SystemAccount<'info>,
+    pub payables_vault: &'a SystemAccount<'info>,
+    pub pool_vault: &'a SystemAccount<'info>,
+    pub player_state: &'a mut Account<'info, PlayerState>,
+    pub referrer: Option<Referrer<'a>>,
+    pub sysvar_instructions: &'a UncheckedAccount<'info>,
+    pub system_program: &'a Program<'info, System>,
+}
+
+// Second and third tier states, checked against the `current_referrer` chain starting at
+// `second_key`. The chain runs on through the buyer or the referrer, whose states are not
+// passed again; `process_referral` checks which tiers must be passed.
+fn upline<'a>(
+    authority: Pubkey,
+    player: Pubkey,
+    referrer: Pubkey,
+    second_key: Pubkey,
+    second: Option<&'a mut Box<Account<'_, PlayerState>>>,
+    third: Option<&'a mut Box<Account<'_, PlayerState>>>,
+) -> Result<[Option<Upline<'a>>; 2]> {
+    let third_key = match second.as_ref() {
+        Some(state) => state.current_referrer,
+        None if second_key == player => referrer,
+        None if second_key == referrer => second_key,
+        None => Pubkey::default(),
+    };
+
+    if let Some(state) = second.as_ref() {
+        check_pda(state.key(), &[second_key.as_ref(), PlayerState::SEED, authority.as_ref()], CustomErrors::InvalidReferrer)?;
+    }
+    if let Some(state) = third.as_ref() {
+        check_pda(state.key(), &[third_key.as_ref(), PlayerState::SEED, authority.as_ref()], CustomErrors::InvalidReferrer)?;
+    }
+
+    Ok([
+        second.map(|state| Upline { key: second_key, state }),
+        third.map(|state| Upline { key: third_key, state }),
+    ])
+}
+
+impl<'info> BuyAccounts<'info> {
+    pub fn parts(&mut self) -> Result<BuyParts<'_, 'info>> {
+        let referrer = self.referrer.as_ref().map_or(self.player_state.current_referrer, |r| r.key());
+        let second_key = self.referrer_state.as_ref().map(|state| state.current_referrer).unwrap_or_default();
+        let authority = self.authority.key();
+
+        // A player with a referrer (or a buy naming one) must pass the referrer's
+        // accounts, whose seeds tie them to that referrer
+        let referrer = if referrer == Pubkey::default() {
+            None
+        } else {
+            let (Some(referrer_state), Some(referrer_stats)) = (self.referrer_state.as_mut(), self.referrer_stats.as_mut()) else {
+                return err!(CustomErrors::InvalidReferrer);
+            };
+            Some(Referrer {
+                key: referrer,
+                state: referrer_state,
+                stats: referrer_stats,
+                registered: self.referrer_profile.as_ref().is_some_and(|profile| is_registered(profile)),
+                upline: upline(authority, self.player.key(), referrer, second_key, self.second_tier_state.as_mut(), self.third_tier_state.as_mut())?,
+            })
+        };
+
+        Ok(BuyParts {
+            payer: &self.payer,
+            player: &self.player,
+            game_state: &mut self.game_state,
+            dev_vault: &self.dev_vault,
+            premarket_vault: &self.premarket_vault,
+            payables_vault: &self.payables_vault,
+            pool_vault: &self.pool_vault,
+            player_state: &mut self.player_state,
+            referrer,
+            sysvar_instructions: &self.sysvar_instructions,
+            system_program: &self.system_program,
+        })
+    }
+}
+
+impl<'info> BuyByUsernameAccounts<'info> {
+    pub fn parts(&mut self) -> Result<BuyParts<'_, 'info>> {
+        let second_key = self.referrer_state.current_referrer;
+        let referrer = self.referrer_username.address;
+        let authority = self.authority.key();
+        Ok(BuyParts {
+            payer: &self.payer,
+            player: &self.player,
+            game_state: &mut self.game_state,
+            dev_vault: &self.dev_vault,
+            premarket_vault: &self.premarket_vault,
+            payables_vault: &self.payables_vault,
+            pool_vault: &self.pool_vault,
+            player_state: &mut self.player_state,
+            referrer: Some(Referrer {
+                key: referrer,
+                state: &mut self.referrer_state,
+                stats: &mut self.referrer_stats,
+                registered: is_registered(&self.referrer_profile),
+                upline: upline(authority, self.player.key(), referrer, second_key, self.second_tier_state.as_mut(), self.third_tier_state.as_mut())?,
+            }),
+            sysvar_instructions: &self.sysvar_instructions,
+            system_program: &self.system_program,
+        })
+    }
+}
+
 #[derive(Accounts)]
 pub struct SellAndHatchAccounts<'info> {
     #[account(mut)]
@@ -71,6 +309,18 @@ pub struct SellAndHatchAccounts<'info> {
     )]
     pub game_state: Account<'info, GameState>,
 
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
     #[account(mut,
         seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
         bump
@@ -87,4 +337,28 @@ pub struct SellAndHatchAccounts<'info> {
     pub sysvar_instructions: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
-}
\ No newline at end of file
+}
+#[derive(Accounts)]
+pub struct QuoteEggsAccounts<'info> {
+    /// CHECK: Can be any player
+    #[account()]
+    pub player: AccountInfo<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    pub nft_asset: Option<Account<'info, BaseAssetV1>>,
+}
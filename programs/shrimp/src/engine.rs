@@ -0,0 +1,1752 @@
+//! Game rules on plain `GameState` / `PlayerState` values with the clock passed in.
+//!
+//! Every function validates, prices and mutates the states it is given, and
+//! returns the effects the Anchor handler still has to apply: lamport transfers
+//! between the payer, the vaults and the player, and event fields. Nothing here touches accounts or sysvars, so the rules run
+//! under plain `cargo test` and in the off-chain simulator.
+
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+const INFLATION_FACTOR: u128 = 1 << 64; // Equivalent to 2**64
+
+/// The buying side of `buy_shrimp` / `buy_premarket`
+pub struct Buyer<'a> {
+    pub key: Pubkey,
+    pub payer: Pubkey,
+    pub state: &'a mut PlayerState,
+    pub referrer: Option<Referrer<'a>>,
+}
+
+/// The referrer of a buy, with whether their global `PlayerProfile` holds a username
+pub struct Referrer<'a> {
+    pub key: Pubkey,
+    pub state: &'a mut PlayerState,
+    pub stats: &'a mut ReferralStats,
+    pub registered: bool,
+    pub upline: [Option<Upline<'a>>; 2], // Second and third tier, when paid
+}
+
+/// The referrer's own referrer (second tier) or theirs (third tier)
+pub struct Upline<'a> {
+    pub key: Pubkey,
+    pub state: &'a mut PlayerState,
+}
+
+/// Lamports a buy credits to the referrer, the buyer and the referrer's upline
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReferralAmounts {
+    pub referral_fee: u64,       // Credited to the referrer
+    pub cashback: u64,           // Credited to the buyer
+    pub tier_fees: [u64; 2],     // Credited to the second and third tier
+    pub expired: bool,           // The referrer's attribution window closed with this buy
+}
+
+impl ReferralAmounts {
+    pub fn total(&self) -> Result<u64> {
+        Ok(self.referral_fee
+            .checked_add(self.cashback).ok_or(CustomErrors::MathOverflow)?
+            .checked_add(self.tier_fees[0]).ok_or(CustomErrors::MathOverflow)?
+            .checked_add(self.tier_fees[1]).ok_or(CustomErrors::MathOverflow)?)
+    }
+}
+
+/// Lamports moved into or out of each vault by one action
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VaultAmounts {
+    pub dev: u64,                // Dev vault (`dev_balance`)
+    pub premarket: u64,          // Premarket vault (`premarket_balance`)
+    pub payables: u64,           // Payables vault (`sell_and_ref_balance`)
+    pub pool: u64,               // Pool vault (`pool_balance`)
+}
+
+impl VaultAmounts {
+    pub fn total(&self) -> Result<u64> {
+        Ok(self.dev
+            .checked_add(self.premarket).ok_or(CustomErrors::MathOverflow)?
+            .checked_add(self.payables).ok_or(CustomErrors::MathOverflow)?
+            .checked_add(self.pool).ok_or(CustomErrors::MathOverflow)?)
+    }
+}
+
+pub struct BuyEffects {
+    pub deposits: VaultAmounts,  // Lamports the payer sends to each vault
+    pub quote: BuyQuote,
+    pub referral: ReferralAmounts, // Referral fee, cashback and tier fees credited by the buy
+}
+
+pub struct PremarketBuyEffects {
+    pub deposits: VaultAmounts,  // Lamports the payer sends to each vault
+    pub dev_fee: u64,
+    pub referral: ReferralAmounts, // Referral fee, cashback and tier fees credited by the buy
+}
+
+pub struct SellEffects {
+    pub from_pool: VaultAmounts, // Lamports the pool vault sends to each other vault
+    pub quote: SellQuote,
+}
+
+pub struct HatchAndSellEffects {
+    pub sell: SellEffects,
+    pub hatch: Option<HatchQuote>, // None when the sell ended the game
+}
+
+pub struct WithdrawEffects {
+    pub payout: u64,             // Lamports sent to the player
+    pub sources: VaultAmounts,   // Lamports each vault sends to the player
+}
+
+// Split a buy of `amount` into the fee and referral vaults, with the rest to the pool
+fn buy_deposits(amount: u64, dev_fee: u64, premarket_fee: u64, referral: &ReferralAmounts) -> Result<VaultAmounts> {
+    let payables = referral.total()?;
+    let pool = amount
+        .checked_sub(dev_fee).ok_or(CustomErrors::MathOverflow)?
+        .checked_sub(premarket_fee).ok_or(CustomErrors::MathOverflow)?
+        .checked_sub(payables).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(VaultAmounts { dev: dev_fee, premarket: premarket_fee, payables, pool })
+}
+
+// Trading (buy, sell, hatch) is only open after the premarket and before the game ends
+pub fn require_market_open(game_state: &GameState, now: u64) -> Result<()> {
+    require!(
+        now > game_state.premarket_end,
+        CustomErrors::PreMarketInProgress
+    );
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+    Ok(())
+}
+
+// Price a market buy of `amount` lamports against the current pool balance
+pub fn quote_buy(game_state: &GameState, amount: u64) -> Result<BuyQuote> {
+    let game_balance = game_state.pool_balance;
+    let eggs = calculate_egg_buy(amount as u128, game_balance as u128, game_state.market_eggs, &game_state.curve, &game_state.fees)?;
+    let (dev_fee, premarket_fee) = calculate_fees(amount, &game_state.fees)?;
+
+    Ok(BuyQuote {
+        game_balance,
+        eggs,
+        shrimp: eggs.checked_div(game_state.curve.eggs_to_hatch_1shrimp).unwrap_or_default(),
+        dev_fee,
+        premarket_fee,
+    })
+}
+
+// Price selling all of the player's eggs, including bonuses and fees
+pub fn quote_sell(
+    game_state: &GameState,
+    player_state: &PlayerState,
+    bonus_percent: u128,
+    now: u64,
+) -> Result<SellQuote> {
+    let eggs = get_my_eggs_at(player_state, game_state, now)?;
+    quote_sell_eggs(game_state, eggs, bonus_percent)
+}
+
+// Price selling `eggs` of the player's eggs (before bonuses), including bonuses and fees
+pub fn quote_sell_eggs(
+    game_state: &GameState,
+    eggs: u128,
+    bonus_percent: u128,
+) -> Result<SellQuote> {
+    let game_balance = game_state.pool_balance;
+    let eggs = apply_bonus(eggs, bonus_percent)?;
+
+    // Ensure that the player has at least 1 full egg unit (as defined by eggs_to_hatch_1shrimp)
+    require!(eggs >= game_state.curve.eggs_to_hatch_1shrimp, CustomErrors::NoEggs);
+
+    // A sell past the endgame limit only sells the eggs that fit under it and ends the game
+    let room = game_state.curve.endgame_limit.saturating_sub(game_state.market_eggs);
+    let sold = eggs.min(room);
+
+    let mut quote = SellQuote {
+        game_balance,
+        eggs: sold,
+        eggs_forfeited: eggs - sold,
+        bonus_percent: bonus_percent as u8,
+        sol_amount: 0,
+        dev_fee: 0,
+        premarket_fee: 0,
+        sol_received: 0,
+        ends_game: eggs > room,
+    };
+
+    // Nothing fits under the limit
+    if sold == 0 {
+        return Ok(quote);
+    }
+
+    let sol_amount = calculate_egg_sell(sold, game_state.market_eggs, game_balance as u128, &game_state.curve)?;
+    quote.sol_amount = u64::try_from(sol_amount).map_err(|_| error!(CustomErrors::MathOverflow))?;
+    (quote.dev_fee, quote.premarket_fee) = calculate_fees(quote.sol_amount, &game_state.fees)?;
+
+    // The user receives the egg sell value minus the fees
+    quote.sol_received = quote.sol_amount
+        .checked_sub(quote.dev_fee).ok_or(CustomErrors::MathOverflow)?
+        .checked_sub(quote.premarket_fee).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(quote)
+}
+
+// Price hatching all of the player's eggs, including bonuses
+pub fn quote_hatch(
+    game_state: &GameState,
+    player_state: &PlayerState,
+    bonus_percent: u128,
+    now: u64,
+) -> Result<HatchQuote> {
+    let eggs = apply_bonus(get_my_eggs_at(player_state, game_state, now)?, bonus_percent)?;
+
+    // Convert eggs to shrimp using the conversion ratio
+    let shrimp = eggs.checked_div(game_state.curve.eggs_to_hatch_1shrimp).unwrap_or_default();
+
+    // Must have at least 1 egg
+    require!(shrimp >= 1, CustomErrors::NoEggs);
+
+    Ok(HatchQuote {
+        eggs,
+        bonus_percent: bonus_percent as u8,
+        shrimp,
+    })
+}
+
+/// Rules of `buy_shrimp`
+pub fn buy_shrimp(
+    game_state: &mut GameState,
+    buyer: Buyer,
+    amount: u64,
+    min_shrimp_out: u128,
+    now: u64,
+) -> Result<BuyEffects> {
+    // Check amount
+    require!(
+        amount >= MIN_BUY,
+        CustomErrors::BuyAmountTooLow
+    );
+    require_market_open(game_state, now)?;
+
+    // Price the buy against the current pool balance
+    let quote = quote_buy(game_state, amount)?;
+
+    // Slippage protection: fail if the market moved against the buyer
+    require!(
+        quote.shrimp >= min_shrimp_out,
+        CustomErrors::SlippageExceeded
+    );
+
+    // Calculate current eggs and add them to extra_eggs if present
+    let player_state = buyer.state;
+    let new_eggs = get_eggs_since_last_hatch_at(player_state, game_state, now)?;
+    if new_eggs > 0 {
+        player_state.extra_eggs = player_state.extra_eggs.checked_add(new_eggs).unwrap_or(player_state.extra_eggs);
+    }
+
+    // Add dev fee to dev balance
+    game_state.dev_balance = game_state.dev_balance
+        .checked_add(quote.dev_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+
+    // Add to premarket balance
+    game_state.premarket_earned = game_state.premarket_earned
+        .checked_add(quote.premarket_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+    game_state.premarket_balance = game_state.premarket_balance
+        .checked_add(quote.premarket_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+
+    // Convert eggs to shrimp
+    player_state.shrimp = player_state.shrimp.checked_add(quote.shrimp).unwrap_or(player_state.shrimp);
+    game_state.total_shrimp = game_state.total_shrimp.checked_add(quote.shrimp).ok_or(CustomErrors::MathOverflow)?;
+
+    // Handle referrals
+    let mut referral = ReferralAmounts::default();
+    if let Some(referrer) = buyer.referrer {
+        referral = process_referral(game_state, player_state, buyer.key, referrer, amount, buyer.payer, now)?;
+    }
+
+    // The rest of the buy goes to the pool
+    let deposits = buy_deposits(amount, quote.dev_fee, quote.premarket_fee, &referral)?;
+    game_state.pool_balance = game_state.pool_balance.checked_add(deposits.pool).ok_or(CustomErrors::MathOverflow)?;
+
+    // Update last interaction and market spend
+    player_state.last_interaction = now;
+    player_state.market_spent = player_state.market_spent.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+    game_state.market_spent = game_state.market_spent.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(BuyEffects { deposits, quote, referral })
+}
+
+/// Rules of `buy_premarket`
+pub fn buy_premarket(
+    game_state: &mut GameState,
+    buyer: Buyer,
+    amount: u64,
+    now: u64,
+) -> Result<PremarketBuyEffects> {
+    // Check amount
+    require!(
+        amount >= MIN_BUY,
+        CustomErrors::BuyAmountTooLow
+    );
+
+    // Check game is in premarket
+    require!(
+        now < game_state.premarket_end,
+        CustomErrors::PreMarketOver
+    );
+
+    // Handle referrals before the spend is recorded, so a new player can still pick a referrer
+    let player_state = buyer.state;
+    let mut referral = ReferralAmounts::default();
+    if let Some(referrer) = buyer.referrer {
+        referral = process_referral(game_state, player_state, buyer.key, referrer, amount, buyer.payer, now)?;
+    }
+
+    // Update player and game state
+    game_state.premarket_spent = game_state.premarket_spent.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+    player_state.premarket_spent = player_state.premarket_spent.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+
+    // Add dev fee to dev balance
+    let dev_fee = calculate_fees(amount, &game_state.fees)?.0;
+    game_state.dev_balance = game_state.dev_balance.checked_add(dev_fee).ok_or(CustomErrors::MathOverflow)?;
+
+    // The rest of the buy goes to the pool (no premarket fee during the premarket)
+    let deposits = buy_deposits(amount, dev_fee, 0, &referral)?;
+    game_state.pool_balance = game_state.pool_balance.checked_add(deposits.pool).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(PremarketBuyEffects { deposits, dev_fee, referral })
+}
+
+/// Rules of `set_referrer`: replaces the player's referrer when the game's
+/// `ReferrerPolicy` allows it and returns the previous one, whose referee
+/// count (`previous_stats`, required once the player has a referrer) drops
+#[allow(clippy::too_many_arguments)]
+pub fn set_referrer(
+    game_state: &GameState,
+    player: Pubkey,
+    player_state: &mut PlayerState,
+    referrer: Pubkey,
+    referrer_stats: &mut ReferralStats,
+    previous_stats: Option<&mut ReferralStats>,
+    registered: bool,
+    now: u64,
+) -> Result<Pubkey> {
+    // Referrer must have a username in the global profile registry and can't be self
+    require!(registered, CustomErrors::InvalidReferrer);
+    require!(
+        referrer != Pubkey::default() && referrer != player && referrer != player_state.current_referrer,
+        CustomErrors::InvalidReferrer
+    );
+
+    // A player without a referrer can always pick one, later changes follow the policy
+    let previous = player_state.current_referrer;
+    if previous != Pubkey::default() {
+        let unlocked = match game_state.referrer_policy {
+            ReferrerPolicy::Locked => player_state.premarket_spent == 0 && player_state.market_spent == 0,
+            ReferrerPolicy::After { days } => now >= player_state.referrer_set_at.saturating_add(days as u64 * 86400),
+            ReferrerPolicy::Free => true,
+        };
+        require!(unlocked, CustomErrors::ReferrerLocked);
+
+        // The player is no longer the previous referrer's referee
+        let previous_stats = previous_stats.ok_or(CustomErrors::InvalidReferrer)?;
+        previous_stats.referees = previous_stats.referees.saturating_sub(1);
+    }
+
+    // The attribution window keeps running from the player's first referred buy,
+    // so switching referrers (and back) cannot restart it
+    player_state.current_referrer = referrer;
+    player_state.referrer_set_at = now;
+    referrer_stats.referrer = referrer;
+    referrer_stats.referees = referrer_stats.referees.saturating_add(1);
+
+    Ok(previous)
+}
+
+/// Rules of `sell_eggs`. Proceeds are credited to `sell_total` and move from the
+/// pool vault to the payables vault until the player withdraws them. A sell
+/// past the endgame limit is filled up to the limit, forfeits the rest of the
+/// seller's eggs and ends the game.
+pub fn sell_eggs(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    bonus_percent: u128,
+    min_lamports_out: u64,
+    now: u64,
+) -> Result<SellEffects> {
+    require_sell_open(game_state, player_state, now)?;
+
+    // Price the sell: eggs with bonuses, value and fees
+    let quote = quote_sell(game_state, player_state, bonus_percent, now)?;
+
+    settle_sell(game_state, player_state, quote, 0, min_lamports_out, now)
+}
+
+/// Rules of `sell_eggs_partial`: sells `eggs` of the player's eggs (before
+/// bonuses) like `sell_eggs` and keeps the rest in `extra_eggs`
+pub fn sell_eggs_partial(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    eggs: u128,
+    bonus_percent: u128,
+    min_lamports_out: u64,
+    now: u64,
+) -> Result<SellEffects> {
+    require_sell_open(game_state, player_state, now)?;
+
+    // Ensure the player has the eggs they want to sell
+    let available = get_my_eggs_at(player_state, game_state, now)?;
+    require!(
+        eggs > 0 && eggs <= available,
+        CustomErrors::InvalidEggAmount
+    );
+
+    // Price the sell: eggs with bonuses, value and fees
+    let quote = quote_sell_eggs(game_state, eggs, bonus_percent)?;
+
+    settle_sell(game_state, player_state, quote, available - eggs, min_lamports_out, now)
+}
+
+// Selling is open after the premarket, once the player's sell cooldown has passed and before the game ends
+fn require_sell_open(game_state: &GameState, player_state: &PlayerState, now: u64) -> Result<()> {
+    // Ensure the game is out of premarket
+    require!(
+        now > game_state.premarket_end,
+        CustomErrors::PreMarketInProgress
+    );
+
+    // Ensure player sell cooldown is respected
+    require!(
+        now >= player_state.last_sell + game_state.cooldown,
+        CustomErrors::SellCooldownNotReached
+    );
+
+    // Ensure the game is not over already
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+    Ok(())
+}
+
+// Apply a priced sell to the player and the game; `kept_eggs` stay with the player as extra eggs
+fn settle_sell(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    quote: SellQuote,
+    kept_eggs: u128,
+    min_lamports_out: u64,
+    now: u64,
+) -> Result<SellEffects> {
+    // Slippage protection: fail if the market moved against the seller
+    // (including a sell that ends the game with only part of the eggs sold)
+    require!(
+        quote.sol_received >= min_lamports_out,
+        CustomErrors::SlippageExceeded
+    );
+
+    // Update the market eggs with the eggs just sold
+    game_state.market_eggs = game_state.market_eggs.checked_add(quote.eggs).ok_or(CustomErrors::MathOverflow)?;
+
+    // The egg value leaves the pool
+    game_state.pool_balance = game_state.pool_balance.checked_sub(quote.sol_amount).ok_or(CustomErrors::TreasuryUnderflow)?;
+
+    // Add to dev balance
+    game_state.dev_balance = game_state.dev_balance
+        .checked_add(quote.dev_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+
+    // Add to premarket earnings
+    game_state.premarket_earned = game_state.premarket_earned
+        .checked_add(quote.premarket_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+    game_state.premarket_balance = game_state.premarket_balance
+        .checked_add(quote.premarket_fee)
+        .ok_or(CustomErrors::MathOverflow)?;
+
+    // Update the player's sell total and the game's overall sell/referral balance
+    player_state.sell_total = player_state.sell_total.checked_add(quote.sol_received).ok_or(CustomErrors::MathOverflow)?;
+    game_state.sell_and_ref_balance = game_state.sell_and_ref_balance.checked_add(quote.sol_received).ok_or(CustomErrors::MathOverflow)?;
+
+    // Update the player's timestamps
+    player_state.last_interaction = now;
+    player_state.last_sell = now;
+
+    // Keep the unsold eggs (the rest have been sold or forfeited)
+    player_state.extra_eggs = if quote.ends_game { 0 } else { kept_eggs };
+
+    // Check if this sell is ending the game
+    if quote.ends_game {
+        end_game(game_state, now)?;
+    }
+
+    let from_pool = VaultAmounts {
+        dev: quote.dev_fee,
+        premarket: quote.premarket_fee,
+        payables: quote.sol_received,
+        pool: 0,
+    };
+    Ok(SellEffects { from_pool, quote })
+}
+
+/// Rules of `finalize_game`: anyone can end the game once the market has
+/// reached the endgame limit without a sell crossing it
+pub fn finalize_game(game_state: &mut GameState, now: u64) -> Result<()> {
+    // Ensure the game is not over already
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+
+    // Ensure the end condition is met
+    require!(
+        game_state.market_eggs >= game_state.curve.endgame_limit,
+        CustomErrors::EndgameNotReached
+    );
+
+    end_game(game_state, now)
+}
+
+// Close trading and snapshot the pool as the prize, with the total weight it is shared by
+fn end_game(game_state: &mut GameState, now: u64) -> Result<()> {
+    game_state.game_over = true;
+    game_state.game_over_at = now;
+    game_state.final_balance = game_state.pool_balance;
+    game_state.prize_weight = match game_state.prize_mode {
+        PrizeMode::Premarket => game_state.premarket_spent as u128,
+        PrizeMode::Shrimp => get_total_shrimp(game_state)?,
+        PrizeMode::MarketSpent => game_state.market_spent as u128,
+    };
+    Ok(())
+}
+
+// Last second players can withdraw once the game is over
+pub fn claim_deadline(game_state: &GameState) -> Result<u64> {
+    Ok(game_state.game_over_at.checked_add(game_state.claim_window).ok_or(CustomErrors::MathOverflow)?)
+}
+
+/// Rules of `sweep_unclaimed`: once the claim window has passed, empties the
+/// premarket, payables and pool balances and returns what each vault gives up.
+/// The destination is credited with `credit_sweep`.
+pub fn sweep_unclaimed(game_state: &mut GameState, now: u64) -> Result<VaultAmounts> {
+    // Ensure the game is over and the claim window has passed
+    require!(
+        game_state.game_over,
+        CustomErrors::GameNotOver
+    );
+    require!(
+        now > claim_deadline(game_state)?,
+        CustomErrors::ClaimWindowOpen
+    );
+    require!(
+        !game_state.swept,
+        CustomErrors::AlreadySwept
+    );
+
+    // Everything players did not withdraw
+    let swept = VaultAmounts {
+        dev: 0,
+        premarket: game_state.premarket_balance,
+        payables: game_state.sell_and_ref_balance,
+        pool: game_state.pool_balance,
+    };
+    game_state.premarket_balance = 0;
+    game_state.sell_and_ref_balance = 0;
+    game_state.pool_balance = 0;
+    game_state.swept = true;
+
+    Ok(swept)
+}
+
+/// Credit swept lamports to their destination: the game's own dev balance, or
+/// the pool of the next season's game. Returns where the lamports went, which
+/// falls back to the dev vault once the next season is over too.
+pub fn credit_sweep(game_state: &mut GameState, next_game: Option<&mut GameState>, amount: u64) -> Result<SweepDestination> {
+    match (game_state.sweep_destination, next_game) {
+        (SweepDestination::NextSeason { authority }, Some(next_game)) if !next_game.game_over => {
+            next_game.pool_balance = next_game.pool_balance.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+            Ok(SweepDestination::NextSeason { authority })
+        }
+        (SweepDestination::NextSeason { .. }, None) => err!(CustomErrors::InvalidClaimPolicy),
+        _ => {
+            game_state.dev_balance = game_state.dev_balance.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+            Ok(SweepDestination::DevVault)
+        }
+    }
+}
+
+/// Rules of `start_season`: once the current season is over, `next_game`
+/// (already set up like `initialize` would) becomes the next season and takes
+/// over what `carry_over` asks for from `previous_game`
+pub fn start_season(
+    registry: &mut GameRegistry,
+    previous_game: &mut GameState,
+    next_game: &mut GameState,
+    carry_over: SeasonCarryOver,
+) -> Result<()> {
+    // Ensure the previous game is the current season and is over
+    require!(
+        previous_game.authority == registry.authority && previous_game.season == registry.season,
+        CustomErrors::NotCurrentSeason
+    );
+    require!(
+        previous_game.game_over,
+        CustomErrors::GameNotOver
+    );
+
+    // Leftover treasury is swept into the new pool once the previous claim window closes
+    if carry_over.treasury {
+        require!(
+            !previous_game.swept,
+            CustomErrors::AlreadySwept
+        );
+        previous_game.sweep_destination = SweepDestination::NextSeason { authority: next_game.authority };
+    }
+
+    // NFTs of the previous collection keep granting their bonus
+    if carry_over.bonuses {
+        next_game.collection_key = previous_game.collection_key;
+    }
+
+    // Link the seasons
+    let season = registry.season.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    next_game.season = season;
+    next_game.previous_authority = previous_game.authority;
+    next_game.carry_over = carry_over;
+    registry.season = season;
+    registry.authority = next_game.authority;
+
+    Ok(())
+}
+
+/// Rules of `carry_over_player`: copies the bonuses the season carries over from
+/// the player's state in the previous season (usernames live in the global
+/// `PlayerProfile` and need no carry-over)
+pub fn carry_over_player(game_state: &GameState, previous_player: &PlayerState, player_state: &mut PlayerState) -> Result<()> {
+    // Ensure bonuses carry over
+    require!(
+        game_state.carry_over.bonuses,
+        CustomErrors::CarryOverDisabled
+    );
+
+    // Testnet players keep their bonus
+    if previous_player.testnet_player {
+        player_state.testnet_player = true;
+    }
+
+    Ok(())
+}
+
+// A player's weight in the prize under the game's prize mode (frozen once the game is over)
+pub fn prize_weight(game_state: &GameState, player_state: &PlayerState) -> Result<u128> {
+    Ok(match game_state.prize_mode {
+        PrizeMode::Premarket => player_state.premarket_spent as u128,
+        PrizeMode::Shrimp => get_my_shrimp(player_state, game_state)?,
+        PrizeMode::MarketSpent => player_state.market_spent as u128,
+    })
+}
+
+// `amount * weight / total` rounded down. Weights too large for the product are
+// scaled down first, rounding the total up so shares never sum past `amount`.
+fn pro_rata(amount: u64, weight: u128, total: u128) -> Result<u64> {
+    let shift = (u128::BITS - total.leading_zeros()).saturating_sub(u64::BITS);
+    let weight = weight >> shift;
+    let total = (total >> shift) + u128::from(total & ((1u128 << shift) - 1) != 0);
+
+    let share = (amount as u128)
+        .checked_mul(weight).ok_or(CustomErrors::MathOverflow)?
+        .checked_div(total).ok_or(CustomErrors::MathOverflow)?;
+    u64::try_from(share).map_err(|_| error!(CustomErrors::MathOverflow))
+}
+
+/// Rules of `hatch_eggs`
+pub fn hatch_eggs(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    bonus_percent: u128,
+    now: u64,
+) -> Result<HatchQuote> {
+    // Ensure the game is out of premarket
+    require!(
+        now > game_state.premarket_end,
+        CustomErrors::PreMarketInProgress
+    );
+
+    // Ensure player hatch cooldown is respected
+    require!(
+        now >= player_state.last_hatch + game_state.cooldown,
+        CustomErrors::HatchCooldownNotReached
+    );
+
+    // Ensure the game is not over already
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+
+    // Calculate shrimp from eggs, including bonuses
+    let quote = quote_hatch(game_state, player_state, bonus_percent, now)?;
+
+    // Update the player's shrimp count
+    player_state.shrimp = player_state.shrimp.checked_add(quote.shrimp).unwrap_or(player_state.shrimp);
+    game_state.total_shrimp = game_state.total_shrimp.checked_add(quote.shrimp).ok_or(CustomErrors::MathOverflow)?;
+
+    // Reset extra eggs (since they have been hatched)
+    player_state.extra_eggs = 0;
+
+    // Update timestamps
+    player_state.last_interaction = now;
+    player_state.last_hatch = now;
+
+    Ok(quote)
+}
+
+/// Rules of `hatch_and_sell`: sells `sell_bps` of the player's eggs (before
+/// bonuses) like `sell_eggs_partial` and hatches the rest like `hatch_eggs`.
+/// Both cooldowns must have passed. A sell that ends the game forfeits the
+/// rest instead of hatching it.
+pub fn hatch_and_sell(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    sell_bps: u16,
+    bonus_percent: u128,
+    min_lamports_out: u64,
+    now: u64,
+) -> Result<HatchAndSellEffects> {
+    // Ensure both parts get some eggs
+    require!(
+        sell_bps > 0 && sell_bps < SELL_BPS_MAX,
+        CustomErrors::InvalidSellShare
+    );
+
+    // Ensure player hatch cooldown is respected before anything is sold
+    require!(
+        now >= player_state.last_hatch + game_state.cooldown,
+        CustomErrors::HatchCooldownNotReached
+    );
+    require_sell_open(game_state, player_state, now)?;
+
+    // Sell the share of the eggs, keeping the rest as extra eggs
+    let eggs = get_my_eggs_at(player_state, game_state, now)?;
+    let eggs_to_sell = eggs
+        .checked_mul(sell_bps as u128).ok_or(CustomErrors::MathOverflow)?
+        / SELL_BPS_MAX as u128;
+    let sell = sell_eggs_partial(game_state, player_state, eggs_to_sell, bonus_percent, min_lamports_out, now)?;
+    if sell.quote.ends_game {
+        return Ok(HatchAndSellEffects { sell, hatch: None });
+    }
+
+    // Hatch the eggs that were kept
+    let hatch = hatch_eggs(game_state, player_state, bonus_percent, now)?;
+
+    Ok(HatchAndSellEffects { sell, hatch: Some(hatch) })
+}
+
+/// Rules of `user_withdraw`: referral, sell and premarket earnings, plus the
+/// player's share of the final balance (by the game's `PrizeMode`) once the game is over
+pub fn user_withdraw(
+    game_state: &mut GameState,
+    player_state: &mut PlayerState,
+    now: u64,
+) -> Result<WithdrawEffects> {
+    // Claims close at the end of the claim window
+    if game_state.game_over {
+        require!(
+            now <= claim_deadline(game_state)?,
+            CustomErrors::ClaimWindowClosed
+        );
+    }
+
+    let mut amount: u64 = 0;
+    let mut sources = VaultAmounts::default();
+
+    // Calculate referral amount
+    let referral_amount = player_state.referral_total.checked_sub(player_state.referral_withdrawn).ok_or(CustomErrors::MathOverflow)?;
+    amount = amount.checked_add(referral_amount).ok_or(CustomErrors::MathOverflow)?;
+    player_state.referral_withdrawn = player_state.referral_withdrawn.checked_add(referral_amount).ok_or(CustomErrors::MathOverflow)?;
+
+    // Calculate second and third tier amount
+    let tier_amount = player_state.tier_total.checked_sub(player_state.tier_withdrawn).ok_or(CustomErrors::MathOverflow)?;
+    amount = amount.checked_add(tier_amount).ok_or(CustomErrors::MathOverflow)?;
+    player_state.tier_withdrawn = player_state.tier_withdrawn.checked_add(tier_amount).ok_or(CustomErrors::MathOverflow)?;
+
+    if now > game_state.premarket_end {
+        // Calculate sell amount
+        let sell_amount = player_state.sell_total.checked_sub(player_state.sell_withdrawn).ok_or(CustomErrors::MathOverflow)?;
+        amount = amount.checked_add(sell_amount).ok_or(CustomErrors::MathOverflow)?;
+        player_state.sell_withdrawn = player_state.sell_withdrawn.checked_add(sell_amount).ok_or(CustomErrors::MathOverflow)?;
+    }
+
+    // Update game state sell and referral balance
+    game_state.sell_and_ref_balance = game_state.sell_and_ref_balance.checked_sub(amount).ok_or(CustomErrors::TreasuryUnderflow)?;
+    sources.payables = amount;
+
+    // Check if player participated in premarket
+    if player_state.premarket_spent > 0 && game_state.premarket_balance > 0 && now > game_state.premarket_end {
+
+        let player_premarket_share = (player_state.premarket_spent as u128)
+            .checked_mul(INFLATION_FACTOR).ok_or(CustomErrors::MathOverflow)?
+            .checked_div(game_state.premarket_spent as u128).ok_or(CustomErrors::MathOverflow)?;
+
+        // Calculate share of premarket earnings
+        let player_premarket_earned = player_premarket_share
+            .checked_mul(game_state.premarket_earned as u128).ok_or(CustomErrors::MathOverflow)?
+            / INFLATION_FACTOR;
+        let player_premarket_earned = u64::try_from(player_premarket_earned).map_err(|_| error!(CustomErrors::MathOverflow))?;
+
+        let premarket_amount_to_withdraw = player_premarket_earned.checked_sub(player_state.premarket_withdrawn).ok_or(CustomErrors::MathOverflow)?;
+
+        amount = amount.checked_add(premarket_amount_to_withdraw).ok_or(CustomErrors::MathOverflow)?;
+        player_state.premarket_withdrawn = player_state.premarket_withdrawn.checked_add(premarket_amount_to_withdraw).ok_or(CustomErrors::MathOverflow)?;
+        game_state.premarket_balance = game_state.premarket_balance.checked_sub(premarket_amount_to_withdraw).ok_or(CustomErrors::TreasuryUnderflow)?;
+        sources.premarket = premarket_amount_to_withdraw;
+    }
+
+    // Check if game is over and the player has a share of the final balance
+    let weight = prize_weight(game_state, player_state)?;
+    if game_state.game_over && !player_state.prize_withdrawn && weight > 0 {
+        // Calculate share of final balance
+        let player_share_of_final_balance = pro_rata(game_state.final_balance, weight, game_state.prize_weight)?;
+        amount = amount.checked_add(player_share_of_final_balance).ok_or(CustomErrors::MathOverflow)?;
+        player_state.prize_withdrawn = true;
+
+        // The prize is paid out of the pool
+        game_state.pool_balance = game_state.pool_balance.checked_sub(player_share_of_final_balance).ok_or(CustomErrors::TreasuryUnderflow)?;
+        sources.pool = player_share_of_final_balance;
+    }
+
+    // Require non-zero withdrawal amount
+    require!(
+        amount > 0,
+        CustomErrors::InsufficientFunds
+    );
+
+    Ok(WithdrawEffects { payout: amount, sources })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREMARKET_END: u64 = 1_000;
+    const COOLDOWN: u64 = 60;
+
+    // A game the way `initialize` leaves it
+    fn new_game() -> GameState {
+        GameState {
+            market_eggs: MARKET_START,
+            premarket_end: PREMARKET_END,
+            cooldown: COOLDOWN,
+            claim_window: CLAIM_WINDOW,
+            referral_window: REFERRAL_WINDOW,
+            ..Default::default()
+        }
+    }
+
+    fn buyer<'a>(key: Pubkey, state: &'a mut PlayerState) -> Buyer<'a> {
+        Buyer { key, payer: key, state, referrer: None }
+    }
+
+    fn assert_error<T>(result: Result<T>, expected: CustomErrors) {
+        match result {
+            Err(err) => assert_eq!(err, expected.into()),
+            Ok(_) => panic!("expected {:?}", expected),
+        }
+    }
+
+    // Premarket buy of `amount` by a fresh player, returning the player
+    fn premarket_player(game: &mut GameState, amount: u64) -> PlayerState {
+        let mut player = PlayerState::default();
+        buy_premarket(game, buyer(Pubkey::new_unique(), &mut player), amount, PREMARKET_END - 1).unwrap();
+        player
+    }
+
+    #[test]
+    fn premarket_buy_books_dev_fee_and_spend() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let effects = buy_premarket(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY * 10, PREMARKET_END - 1).unwrap();
+
+        assert_eq!(player.premarket_spent, MIN_BUY * 10);
+        assert_eq!(game.premarket_spent, MIN_BUY * 10);
+        assert_eq!(game.dev_balance, MIN_BUY * 10 * DEV_FEE / 100);
+        assert_eq!(game.pool_balance, MIN_BUY * 10 * (100 - DEV_FEE) / 100);
+        assert_eq!(effects.deposits, VaultAmounts {
+            dev: game.dev_balance,
+            premarket: 0,
+            payables: 0,
+            pool: game.pool_balance,
+        });
+    }
+
+    #[test]
+    fn sell_overflowing_u64_is_a_math_error() {
+        let mut game = new_game();
+        game.market_eggs = 1;
+        game.pool_balance = u64::MAX;
+        let mut player = PlayerState { shrimp: 1, last_interaction: PREMARKET_END, ..Default::default() };
+        let result = sell_eggs(&mut game, &mut player, 0, 0, PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64);
+        assert_error(result, CustomErrors::MathOverflow);
+    }
+
+    #[test]
+    fn premarket_buy_rejected_after_premarket() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let result = buy_premarket(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY, PREMARKET_END);
+        assert_error(result, CustomErrors::PreMarketOver);
+    }
+
+    #[test]
+    fn premarket_buy_rejected_below_min_buy() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let result = buy_premarket(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY - 1, 0);
+        assert_error(result, CustomErrors::BuyAmountTooLow);
+    }
+
+    #[test]
+    fn buy_adds_quoted_shrimp_and_fees() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let now = PREMARKET_END + 1;
+
+        let expected = quote_buy(&game, MIN_BUY * 100).unwrap();
+        let effects = buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY * 100, 0, now).unwrap();
+
+        assert_eq!(effects.quote, expected);
+        assert_eq!(effects.deposits.total().unwrap(), MIN_BUY * 100);
+        assert_eq!(effects.deposits.pool, MIN_BUY * 100 - expected.dev_fee - expected.premarket_fee);
+        assert_eq!(game.pool_balance, effects.deposits.pool);
+        assert!(player.shrimp > 0);
+        assert_eq!(player.shrimp, expected.shrimp);
+        assert_eq!(player.market_spent, MIN_BUY * 100);
+        assert_eq!(player.last_interaction, now);
+        assert_eq!(game.premarket_balance, expected.premarket_fee);
+        assert_eq!(game.dev_balance, expected.dev_fee);
+    }
+
+    #[test]
+    fn buy_uses_the_game_fee_schedule() {
+        let mut game = new_game();
+        game.fees = FeeSchedule { dev_fee: 2, premarket_fee: 3, ..Default::default() };
+        let mut player = PlayerState::default();
+
+        let effects = buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!(effects.quote.dev_fee, MIN_BUY * 100 * 2 / 100);
+        assert_eq!(effects.quote.premarket_fee, MIN_BUY * 100 * 3 / 100);
+
+        // Lower fees buy more eggs than the default schedule
+        let default_quote = quote_buy(&new_game(), MIN_BUY * 100).unwrap();
+        assert!(effects.quote.eggs > default_quote.eggs);
+    }
+
+    #[test]
+    fn buy_rejected_during_premarket_and_after_game_over() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let result = buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY, 0, PREMARKET_END);
+        assert_error(result, CustomErrors::PreMarketInProgress);
+
+        game.game_over = true;
+        let result = buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY, 0, PREMARKET_END + 1);
+        assert_error(result, CustomErrors::GameOver);
+    }
+
+    #[test]
+    fn buy_rejected_below_min_shrimp_out() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let quote = quote_buy(&game, MIN_BUY).unwrap();
+        let result = buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY, quote.shrimp + 1, PREMARKET_END + 1);
+        assert_error(result, CustomErrors::SlippageExceeded);
+        assert_eq!(player.shrimp, 0);
+    }
+
+    #[test]
+    fn buy_banks_eggs_produced_at_the_old_rate() {
+        let mut game = new_game();
+        let mut player = PlayerState { shrimp: 10, last_interaction: PREMARKET_END, ..Default::default() };
+        buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut player), MIN_BUY, 0, PREMARKET_END + 100).unwrap();
+        assert_eq!(player.extra_eggs, 10 * 100);
+    }
+
+    #[test]
+    fn buy_credits_registered_referrer() {
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+        let referrer = Pubkey::new_unique();
+
+        // Only referrers with a username in the profile registry are accepted
+        let buyer = Buyer {
+            key: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            state: &mut player,
+            referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: false, upline: [None, None] }),
+        };
+        assert_error(buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1), CustomErrors::InvalidReferrer);
+
+        let buyer = Buyer {
+            key: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            state: &mut player,
+            referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+
+        assert_eq!(player.current_referrer, referrer);
+        assert_eq!(referrer_state.referral_total, MIN_BUY * 100 * REFERRAL_FEE / 100);
+        assert_eq!(player.referral_total, MIN_BUY * 100 * REFERRAL_CASHBACK / 100);
+        assert_eq!(game.sell_and_ref_balance, MIN_BUY * 100 * (REFERRAL_FEE + REFERRAL_CASHBACK) / 100);
+        assert_eq!(effects.deposits.payables, game.sell_and_ref_balance);
+        assert_eq!(effects.deposits.total().unwrap(), MIN_BUY * 100);
+
+        // Fees earned and the cashback are tracked apart in the referrer's statistics
+        assert_eq!((effects.referral.referral_fee, effects.referral.cashback), (referrer_state.referral_total, player.referral_total));
+        assert_eq!(player.cashback_total, player.referral_total);
+        assert_eq!(stats.referrer, referrer);
+        assert_eq!(stats.referees, 1);
+        assert_eq!(stats.referred_volume, MIN_BUY * 100);
+        assert_eq!(stats.fees_earned, referrer_state.referral_total);
+        assert_eq!(stats.referee_cashback, player.referral_total);
+    }
+
+    #[test]
+    fn buy_follows_the_referrer_split() {
+        let mut game = new_game();
+        let key = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState { referee_cashback: 3, ..Default::default() };
+        let mut stats = ReferralStats::default();
+
+        // The split never leaves the referral budget
+        assert!(check_referral_split(&game.fees, REFERRAL_FEE + REFERRAL_CASHBACK).is_ok());
+        assert_error(check_referral_split(&game.fees, REFERRAL_FEE + REFERRAL_CASHBACK + 1), CustomErrors::InvalidReferralSplit);
+        assert_error(check_referral_split(&game.fees, REFERRAL_CASHBACK - 1), CustomErrors::InvalidReferralSplit);
+
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: Pubkey::new_unique(), state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!((effects.referral.referral_fee, effects.referral.cashback), (MIN_BUY * 2, MIN_BUY * 3));
+        assert_eq!(effects.deposits.payables, MIN_BUY * (REFERRAL_FEE + REFERRAL_CASHBACK));
+    }
+
+    #[test]
+    fn buy_pays_the_referral_tiers() {
+        let mut game = new_game();
+        game.referral_tiers = [2, 1];
+        let key = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let third = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState { current_referrer: second, ..Default::default() };
+        let mut second_state = PlayerState { current_referrer: third, ..Default::default() };
+        let mut third_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+
+        // Tiers never take the game's cashback
+        assert!(check_referral_tiers(&game.fees, [REFERRAL_FEE, 0]).is_ok());
+        assert_error(check_referral_tiers(&game.fees, [REFERRAL_FEE, 1]), CustomErrors::InvalidReferralTiers);
+
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer {
+                key: Pubkey::new_unique(),
+                state: &mut referrer_state,
+                stats: &mut stats,
+                registered: true,
+                upline: [
+                    Some(Upline { key: second, state: &mut second_state }),
+                    Some(Upline { key: third, state: &mut third_state }),
+                ],
+            }),
+        };
+        let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+
+        // The tiers come out of the referrer's fee
+        assert_eq!(effects.referral.tier_fees, [MIN_BUY * 2, MIN_BUY]);
+        assert_eq!(effects.referral.referral_fee, MIN_BUY);
+        assert_eq!(effects.referral.cashback, MIN_BUY * REFERRAL_CASHBACK);
+        assert_eq!(second_state.tier_total, MIN_BUY * 2);
+        assert_eq!(third_state.tier_total, MIN_BUY);
+        assert_eq!(effects.deposits.payables, MIN_BUY * (REFERRAL_FEE + REFERRAL_CASHBACK));
+        assert_eq!(game.sell_and_ref_balance, effects.deposits.payables);
+
+        // Tier income is withdrawn with the rest
+        let effects = user_withdraw(&mut game, &mut second_state, PREMARKET_END + 2).unwrap();
+        assert_eq!(effects.payout, MIN_BUY * 2);
+        assert_eq!(second_state.tier_withdrawn, MIN_BUY * 2);
+    }
+
+    #[test]
+    fn buy_rejects_a_wrong_upline() {
+        let mut game = new_game();
+        game.referral_tiers = [2, 1];
+        let key = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState { current_referrer: Pubkey::new_unique(), ..Default::default() };
+        let mut other_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer {
+                key: Pubkey::new_unique(),
+                state: &mut referrer_state,
+                stats: &mut stats,
+                registered: true,
+                upline: [Some(Upline { key: Pubkey::new_unique(), state: &mut other_state }), None],
+            }),
+        };
+        assert_error(buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1), CustomErrors::InvalidReferrer);
+    }
+
+    #[test]
+    fn buy_requires_the_upline_the_tiers_pay() {
+        let mut game = new_game();
+        game.referral_tiers = [0, 1];
+        let key = Pubkey::new_unique();
+        let referrer = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState { current_referrer: Pubkey::new_unique(), ..Default::default() };
+        let mut stats = ReferralStats::default();
+
+        // Leaving out the second tier would skip the third tier's share too
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        assert_error(buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1), CustomErrors::InvalidReferrer);
+
+        // A chain looping back to the buyer needs no upline
+        referrer_state.current_referrer = key;
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!(effects.referral.tier_fees, [0, 0]);
+        assert_eq!(effects.referral.referral_fee, MIN_BUY * REFERRAL_FEE);
+    }
+
+    #[test]
+    fn buy_applies_the_referral_guards() {
+        let mut game = new_game();
+        game.referral_guards = ReferralGuards { min_referrer_spend: MIN_BUY, referee_cap: MIN_BUY * 5 };
+        let key = Pubkey::new_unique();
+        let referrer = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+
+        // Referrers without a stake in the game earn nothing, and their share stays in the pool
+        game.referral_tiers = [2, 1];
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!(effects.referral, ReferralAmounts { cashback: MIN_BUY * REFERRAL_CASHBACK, ..Default::default() });
+        let deposits = effects.deposits;
+        assert_eq!(deposits.payables, MIN_BUY * REFERRAL_CASHBACK);
+        assert_eq!(deposits.pool, MIN_BUY * 100 - deposits.dev - deposits.premarket - deposits.payables);
+        assert_eq!(game.pool_balance, deposits.pool);
+        assert_eq!((player.referrer_earned, referrer_state.referral_total), (0, 0));
+        game.referral_tiers = [0, 0];
+
+        // Income from one referee stops at the cap, the rest goes to the pool
+        referrer_state.market_spent = MIN_BUY;
+        for (now, fee) in [(PREMARKET_END + 2, MIN_BUY * 4), (PREMARKET_END + 3, MIN_BUY)] {
+            let buyer = Buyer {
+                key,
+                payer: key,
+                state: &mut player,
+                referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+            };
+            let effects = buy_shrimp(&mut game, buyer, MIN_BUY * 100, 0, now).unwrap();
+            assert_eq!(effects.referral.referral_fee, fee);
+            assert_eq!(effects.deposits.total().unwrap(), MIN_BUY * 100);
+        }
+        assert_eq!(player.referrer_earned, MIN_BUY * 5);
+        assert_eq!(referrer_state.referral_total, MIN_BUY * 5);
+        assert_eq!(player.cashback_total, MIN_BUY * 3);
+    }
+
+    #[test]
+    fn referral_expires_after_the_window() {
+        let mut game = new_game();
+        let key = Pubkey::new_unique();
+        let referrer = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut referrer_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+        let first = PREMARKET_END + 1;
+
+        let mut buy = |game: &mut GameState, now| {
+            let buyer = Buyer {
+                key,
+                payer: key,
+                state: &mut player,
+                referrer: Some(Referrer { key: referrer, state: &mut referrer_state, stats: &mut stats, registered: true, upline: [None, None] }),
+            };
+            buy_shrimp(game, buyer, MIN_BUY * 100, 0, now).unwrap()
+        };
+
+        // The window runs from the first referred buy
+        assert_eq!(buy(&mut game, first).referral.referral_fee, MIN_BUY * REFERRAL_FEE);
+        assert_eq!(buy(&mut game, first + REFERRAL_WINDOW - 1).referral.referral_fee, MIN_BUY * REFERRAL_FEE);
+
+        // Then the referral budget stays in the pool, and the expiry is reported once
+        let effects = buy(&mut game, first + REFERRAL_WINDOW);
+        assert_eq!(effects.referral, ReferralAmounts { expired: true, ..Default::default() });
+        assert_eq!(effects.deposits.payables, 0);
+        assert!(!buy(&mut game, first + REFERRAL_WINDOW + 1).referral.expired);
+        assert_eq!(player.referred_at, first);
+        assert!(player.referral_expired);
+    }
+
+    #[test]
+    fn referral_guards_are_bounded() {
+        let fees = new_game().fees;
+        let min_cap = MIN_BUY * REFERRAL_FEE / 100;
+        assert!(check_referral_guards(&fees, &ReferralGuards::default()).is_ok());
+        assert!(check_referral_guards(&fees, &ReferralGuards { min_referrer_spend: MAX_MIN_REFERRER_SPEND, referee_cap: min_cap }).is_ok());
+        let result = check_referral_guards(&fees, &ReferralGuards { min_referrer_spend: MAX_MIN_REFERRER_SPEND + 1, referee_cap: 0 });
+        assert_error(result, CustomErrors::InvalidReferralGuards);
+        let result = check_referral_guards(&fees, &ReferralGuards { min_referrer_spend: 0, referee_cap: min_cap - 1 });
+        assert_error(result, CustomErrors::InvalidReferralGuards);
+    }
+
+    #[test]
+    fn referral_window_is_bounded() {
+        let mut game = new_game();
+        assert!(check_referral_window(&game, REFERRAL_WINDOW).is_ok());
+        assert!(check_referral_window(&game, MAX_REFERRAL_WINDOW).is_ok());
+        assert_error(check_referral_window(&game, 0), CustomErrors::InvalidReferralWindow);
+        assert_error(check_referral_window(&game, MAX_REFERRAL_WINDOW + 1), CustomErrors::InvalidReferralWindow);
+        assert_error(check_referral_window(&game, MIN_REFERRAL_WINDOW - 1), CustomErrors::InvalidReferralWindow);
+
+        // Test environments can use short windows
+        game.test_env = true;
+        assert!(check_referral_window(&game, 2).is_ok());
+    }
+
+    #[test]
+    fn buys_keep_the_current_referrer() {
+        let mut game = new_game();
+        let key = Pubkey::new_unique();
+        let mut player = PlayerState::default();
+        let mut first_state = PlayerState::default();
+        let mut other_state = PlayerState::default();
+        let mut stats = ReferralStats::default();
+        let first = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: first, state: &mut first_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        buy_shrimp(&mut game, buyer, MIN_BUY, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!(player.referrer_set_at, PREMARKET_END + 1);
+
+        // A self-paid buy no longer replaces the referrer
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut player,
+            referrer: Some(Referrer { key: other, state: &mut other_state, stats: &mut stats, registered: true, upline: [None, None] }),
+        };
+        assert_error(buy_shrimp(&mut game, buyer, MIN_BUY, 0, PREMARKET_END + 2), CustomErrors::ReferrerMismatch);
+        assert_eq!(player.current_referrer, first);
+    }
+
+    #[test]
+    fn set_referrer_follows_the_policy() {
+        let mut game = new_game();
+        let player = Pubkey::new_unique();
+        let mut state = PlayerState::default();
+        let mut first_stats = ReferralStats::default();
+        let mut other_stats = ReferralStats::default();
+        let first = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        // Anyone can pick a first referrer, and change it before buying under the locked policy
+        assert_eq!(set_referrer(&game, player, &mut state, first, &mut first_stats, None, true, 10).unwrap(), Pubkey::default());
+        assert_error(set_referrer(&game, player, &mut state, other, &mut other_stats, None, true, 20), CustomErrors::InvalidReferrer);
+        set_referrer(&game, player, &mut state, other, &mut other_stats, Some(&mut first_stats), true, 20).unwrap();
+        assert_eq!(state.current_referrer, other);
+        assert_eq!(state.referrer_set_at, 20);
+        assert_eq!((first_stats.referees, other_stats.referees), (0, 1));
+        assert_error(set_referrer(&game, player, &mut state, player, &mut first_stats, Some(&mut other_stats), true, 20), CustomErrors::InvalidReferrer);
+
+        state.market_spent = MIN_BUY;
+        assert_error(set_referrer(&game, player, &mut state, first, &mut first_stats, Some(&mut other_stats), true, 30), CustomErrors::ReferrerLocked);
+
+        game.referrer_policy = ReferrerPolicy::After { days: 1 };
+        assert_error(set_referrer(&game, player, &mut state, first, &mut first_stats, Some(&mut other_stats), true, 20 + 86399), CustomErrors::ReferrerLocked);
+        assert_eq!(set_referrer(&game, player, &mut state, first, &mut first_stats, Some(&mut other_stats), true, 20 + 86400).unwrap(), other);
+
+        // Switching back and forth keeps one referee in total and the same attribution window
+        game.referrer_policy = ReferrerPolicy::Free;
+        state.referred_at = 15;
+        state.referral_expired = true;
+        set_referrer(&game, player, &mut state, other, &mut other_stats, Some(&mut first_stats), true, 20 + 86401).unwrap();
+        assert_eq!(state.current_referrer, other);
+        assert_eq!((first_stats.referees, other_stats.referees), (0, 1));
+        assert_eq!(state.referred_at, 15);
+        assert!(state.referral_expired);
+    }
+
+    #[test]
+    fn hatch_converts_eggs_and_respects_cooldown() {
+        let mut game = new_game();
+        let mut player = PlayerState {
+            shrimp: 10,
+            last_interaction: PREMARKET_END,
+            last_hatch: PREMARKET_END,
+            ..Default::default()
+        };
+
+        let result = hatch_eggs(&mut game, &mut player, 0, PREMARKET_END + COOLDOWN - 1);
+        assert_error(result, CustomErrors::HatchCooldownNotReached);
+
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let quote = hatch_eggs(&mut game, &mut player, 0, now).unwrap();
+        assert_eq!(quote.shrimp, 10);
+        assert_eq!(player.shrimp, 20);
+        assert_eq!(game.total_shrimp, 10);
+        assert_eq!(player.last_hatch, now);
+        assert_eq!(player.extra_eggs, 0);
+    }
+
+    #[test]
+    fn hatch_applies_bonus_and_requires_a_full_shrimp() {
+        let mut game = new_game();
+        let mut player = PlayerState { shrimp: 100, last_interaction: PREMARKET_END, ..Default::default() };
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let quote = hatch_eggs(&mut game, &mut player.clone(), NFT_BONUS, now).unwrap();
+        assert_eq!(quote.shrimp, 110);
+
+        player.shrimp = 0;
+        assert_error(hatch_eggs(&mut game, &mut player, 0, now), CustomErrors::NoEggs);
+    }
+
+    #[test]
+    fn sell_credits_proceeds_after_fees() {
+        let mut game = new_game();
+        let mut buyer_state = PlayerState::default();
+        buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut buyer_state), MIN_BUY * 1000, 0, PREMARKET_END + 1).unwrap();
+
+        let now = PREMARKET_END + 1 + EGGS_TO_HATCH_1SHRIMP as u64;
+        let market_eggs = game.market_eggs;
+        let pool_balance = game.pool_balance;
+        let effects = sell_eggs(&mut game, &mut buyer_state, 0, 0, now).unwrap();
+        let quote = effects.quote;
+
+        assert!(!quote.ends_game);
+        assert!(quote.sol_received > 0);
+        assert_eq!(quote.sol_received, quote.sol_amount - quote.dev_fee - quote.premarket_fee);
+        assert_eq!(buyer_state.sell_total, quote.sol_received);
+        assert_eq!(buyer_state.last_sell, now);
+        assert_eq!(game.market_eggs, market_eggs + quote.eggs);
+        assert_eq!(game.sell_and_ref_balance, quote.sol_received);
+
+        // The whole egg value leaves the pool for the other vaults
+        assert_eq!(effects.from_pool.total().unwrap(), quote.sol_amount);
+        assert_eq!(game.pool_balance, pool_balance - quote.sol_amount);
+    }
+
+    #[test]
+    fn sell_rejected_during_cooldown_and_below_min_lamports_out() {
+        let mut game = new_game();
+        let mut player = PlayerState {
+            shrimp: 10,
+            last_interaction: PREMARKET_END,
+            last_sell: PREMARKET_END,
+            ..Default::default()
+        };
+
+        let result = sell_eggs(&mut game, &mut player, 0, 0, PREMARKET_END + 1);
+        assert_error(result, CustomErrors::SellCooldownNotReached);
+
+        let result = sell_eggs(&mut game, &mut player, 0, u64::MAX, PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64);
+        assert_error(result, CustomErrors::SlippageExceeded);
+        assert_eq!(player.sell_total, 0);
+    }
+
+    #[test]
+    fn partial_sell_keeps_the_unsold_eggs() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY * 500;
+        let mut player = PlayerState { shrimp: 10, last_interaction: PREMARKET_END, ..Default::default() };
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let eggs = 10 * EGGS_TO_HATCH_1SHRIMP;
+
+        let result = sell_eggs_partial(&mut game, &mut player, eggs + 1, 0, 0, now);
+        assert_error(result, CustomErrors::InvalidEggAmount);
+        assert_error(sell_eggs_partial(&mut game, &mut player, 0, 0, 0, now), CustomErrors::InvalidEggAmount);
+
+        let effects = sell_eggs_partial(&mut game, &mut player, 4 * EGGS_TO_HATCH_1SHRIMP, NFT_BONUS, 0, now).unwrap();
+        assert_eq!(effects.quote.eggs, 4 * EGGS_TO_HATCH_1SHRIMP * 110 / 100);
+        assert_eq!(player.extra_eggs, 6 * EGGS_TO_HATCH_1SHRIMP);
+        assert_eq!(player.last_interaction, now);
+        assert_eq!(player.last_sell, now);
+        assert_eq!(player.sell_total, effects.quote.sol_received);
+
+        // The kept eggs are subject to the same cooldown
+        let result = sell_eggs_partial(&mut game, &mut player, EGGS_TO_HATCH_1SHRIMP, 0, 0, now + 1);
+        assert_error(result, CustomErrors::SellCooldownNotReached);
+    }
+
+    #[test]
+    fn hatch_and_sell_splits_the_eggs() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY * 500;
+        let mut player = PlayerState {
+            shrimp: 10,
+            last_interaction: PREMARKET_END,
+            last_hatch: PREMARKET_END,
+            ..Default::default()
+        };
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+
+        assert_error(hatch_and_sell(&mut game, &mut player, 0, 0, 0, now), CustomErrors::InvalidSellShare);
+        assert_error(hatch_and_sell(&mut game, &mut player, SELL_BPS_MAX, 0, 0, now), CustomErrors::InvalidSellShare);
+        let result = hatch_and_sell(&mut game, &mut player, 3000, 0, 0, PREMARKET_END + COOLDOWN - 1);
+        assert_error(result, CustomErrors::HatchCooldownNotReached);
+
+        let effects = hatch_and_sell(&mut game, &mut player, 3000, 0, 0, now).unwrap();
+        assert_eq!(effects.sell.quote.eggs, 3 * EGGS_TO_HATCH_1SHRIMP);
+        assert_eq!(effects.hatch.unwrap().shrimp, 7);
+        assert_eq!(player.shrimp, 17);
+        assert_eq!(player.extra_eggs, 0);
+        assert_eq!(player.last_sell, now);
+        assert_eq!(player.last_hatch, now);
+        assert_eq!(player.sell_total, effects.sell.quote.sol_received);
+    }
+
+    #[test]
+    fn hatch_and_sell_past_endgame_limit_hatches_nothing() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY * 500;
+        game.market_eggs = ENDGAME_LIMIT;
+        let mut player = PlayerState { shrimp: 10, last_interaction: PREMARKET_END, ..Default::default() };
+
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let effects = hatch_and_sell(&mut game, &mut player, 5000, 0, 0, now).unwrap();
+        assert!(effects.sell.quote.ends_game);
+        assert!(effects.hatch.is_none());
+        assert!(game.game_over);
+        assert_eq!(player.shrimp, 10);
+        assert_eq!(player.extra_eggs, 0);
+    }
+
+    #[test]
+    fn sell_past_endgame_limit_ends_the_game() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY * 500;
+        game.market_eggs = ENDGAME_LIMIT;
+        let mut player = PlayerState { shrimp: 1, last_interaction: PREMARKET_END, ..Default::default() };
+
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let effects = sell_eggs(&mut game, &mut player, 0, 0, now).unwrap();
+
+        assert!(effects.quote.ends_game);
+        assert!(game.game_over);
+        assert_eq!(game.final_balance, MIN_BUY * 500);
+        assert_eq!(effects.from_pool, VaultAmounts::default());
+        assert_eq!(effects.quote.eggs_forfeited, EGGS_TO_HATCH_1SHRIMP);
+        assert_eq!(game.market_eggs, ENDGAME_LIMIT);
+        assert_eq!(player.sell_total, 0);
+        assert_eq!(player.extra_eggs, 0);
+        assert_error(hatch_eggs(&mut game, &mut player, 0, now + COOLDOWN), CustomErrors::GameOver);
+    }
+
+    #[test]
+    fn sell_crossing_endgame_limit_is_filled_up_to_it() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY * 500;
+        game.curve.endgame_limit = MARKET_START * 2;
+        let mut player = PlayerState { shrimp: MARKET_START / EGGS_TO_HATCH_1SHRIMP * 3, last_interaction: PREMARKET_END, ..Default::default() };
+
+        let now = PREMARKET_END + EGGS_TO_HATCH_1SHRIMP as u64;
+        let effects = sell_eggs(&mut game, &mut player, 0, 0, now).unwrap();
+        let quote = effects.quote;
+
+        // Only the eggs up to the limit are sold, at the curve price
+        assert!(quote.ends_game);
+        assert_eq!(quote.eggs, MARKET_START);
+        assert_eq!(quote.eggs_forfeited, MARKET_START * 2);
+        assert!(quote.sol_received > 0);
+        assert_eq!(player.sell_total, quote.sol_received);
+        assert_eq!(game.market_eggs, game.curve.endgame_limit);
+
+        // The prize is the pool left after the sell
+        assert!(game.game_over);
+        assert_eq!(game.final_balance, MIN_BUY * 500 - quote.sol_amount);
+        assert_eq!(game.final_balance, game.pool_balance);
+    }
+
+    #[test]
+    fn finalize_requires_the_endgame_limit_once() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY;
+        assert_error(finalize_game(&mut game, PREMARKET_END + 1), CustomErrors::EndgameNotReached);
+
+        game.market_eggs = ENDGAME_LIMIT;
+        finalize_game(&mut game, PREMARKET_END + 1).unwrap();
+        assert!(game.game_over);
+        assert_eq!(game.final_balance, MIN_BUY);
+
+        assert_error(finalize_game(&mut game, PREMARKET_END + 1), CustomErrors::GameOver);
+    }
+
+    #[test]
+    fn curve_sets_hatch_rate_and_endgame_limit() {
+        let mut game = new_game();
+        game.pool_balance = MIN_BUY;
+        game.curve = CurveParams { eggs_to_hatch_1shrimp: 3600, endgame_limit: MARKET_START * 2, ..Default::default() };
+        let mut player = PlayerState { shrimp: 10, last_interaction: PREMARKET_END, ..Default::default() };
+
+        // An hour of production hatches one shrimp per shrimp
+        let now = PREMARKET_END + 3600;
+        let quote = hatch_eggs(&mut game, &mut player.clone(), 0, now).unwrap();
+        assert_eq!(quote.shrimp, 10);
+
+        // Selling more eggs than the market holds passes the lower endgame limit
+        player.shrimp = MARKET_START / 3600 + 1;
+        let effects = sell_eggs(&mut game, &mut player, 0, 0, now).unwrap();
+        assert!(effects.quote.ends_game);
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn withdraw_pays_referral_and_sell_earnings() {
+        let mut game = new_game();
+        game.sell_and_ref_balance = 300;
+        let mut player = PlayerState { referral_total: 100, sell_total: 200, ..Default::default() };
+
+        // Sell earnings stay locked until the premarket is over
+        let effects = user_withdraw(&mut game, &mut player, PREMARKET_END - 1).unwrap();
+        assert_eq!(effects.payout, 100);
+
+        let effects = user_withdraw(&mut game, &mut player, PREMARKET_END + 1).unwrap();
+        assert_eq!(effects.payout, 200);
+        assert_eq!(effects.sources, VaultAmounts { payables: 200, ..Default::default() });
+        assert_eq!(game.sell_and_ref_balance, 0);
+
+        assert_error(user_withdraw(&mut game, &mut player, PREMARKET_END + 2), CustomErrors::InsufficientFunds);
+    }
+
+    #[test]
+    fn withdraw_splits_premarket_earnings_and_final_balance() {
+        let mut game = new_game();
+        let mut small = premarket_player(&mut game, MIN_BUY);
+        let mut large = premarket_player(&mut game, MIN_BUY * 3);
+
+        game.premarket_earned = 4_000;
+        game.premarket_balance = 4_000;
+        game.game_over = true;
+        game.final_balance = 8_000;
+        game.pool_balance = 8_000;
+        game.prize_weight = game.premarket_spent as u128;
+
+        let now = PREMARKET_END + 1;
+        let effects = user_withdraw(&mut game, &mut small, now).unwrap();
+        assert_eq!(effects.payout, 1_000 + 2_000);
+        assert_eq!(effects.sources, VaultAmounts { premarket: 1_000, pool: 2_000, ..Default::default() });
+        assert_eq!(user_withdraw(&mut game, &mut large, now).unwrap().payout, 3_000 + 6_000);
+        assert!(small.prize_withdrawn && large.prize_withdrawn);
+        assert_eq!(game.premarket_balance, 0);
+        assert_eq!(game.pool_balance, 0);
+
+        // The prize is paid once
+        assert_error(user_withdraw(&mut game, &mut large, now), CustomErrors::InsufficientFunds);
+    }
+
+    #[test]
+    fn shrimp_prize_mode_pays_market_buyers() {
+        let mut game = new_game();
+        game.prize_mode = PrizeMode::Shrimp;
+        let mut early = premarket_player(&mut game, MIN_BUY * 10);
+        let mut late = PlayerState::default();
+        buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut late), MIN_BUY * 10, 0, PREMARKET_END + 1).unwrap();
+        assert_eq!(game.total_shrimp, late.shrimp);
+
+        game.market_eggs = ENDGAME_LIMIT;
+        finalize_game(&mut game, PREMARKET_END + 1).unwrap();
+
+        // Every shrimp, premarket ones included, is in the snapshot
+        let early_shrimp = get_my_shrimp(&early, &game).unwrap();
+        assert_eq!(game.prize_weight, get_total_shrimp(&game).unwrap());
+        assert!(game.prize_weight >= early_shrimp + late.shrimp);
+
+        let now = PREMARKET_END + 2;
+        let late_prize = user_withdraw(&mut game, &mut late, now).unwrap().sources.pool;
+        let early_prize = user_withdraw(&mut game, &mut early, now).unwrap().sources.pool;
+        assert_eq!(late_prize as u128, game.final_balance as u128 * late.shrimp / game.prize_weight);
+        assert_eq!(early_prize as u128, game.final_balance as u128 * early_shrimp / game.prize_weight);
+        assert!(late_prize + early_prize <= game.final_balance);
+    }
+
+    #[test]
+    fn market_spent_prize_mode_ignores_premarket_spend() {
+        let mut game = new_game();
+        game.prize_mode = PrizeMode::MarketSpent;
+        let mut early = premarket_player(&mut game, MIN_BUY * 10);
+        let mut late = PlayerState::default();
+        buy_shrimp(&mut game, buyer(Pubkey::new_unique(), &mut late), MIN_BUY * 10, 0, PREMARKET_END + 1).unwrap();
+
+        game.market_eggs = ENDGAME_LIMIT;
+        finalize_game(&mut game, PREMARKET_END + 1).unwrap();
+        assert_eq!(game.prize_weight, (MIN_BUY * 10) as u128);
+
+        let now = PREMARKET_END + 2;
+        assert_eq!(user_withdraw(&mut game, &mut late, now).unwrap().sources.pool, game.final_balance);
+        assert_eq!(user_withdraw(&mut game, &mut early, now).unwrap().sources.pool, 0);
+    }
+
+    #[test]
+    fn sweep_waits_for_the_claim_window() {
+        let mut game = new_game();
+        let mut player = premarket_player(&mut game, MIN_BUY);
+        assert_error(sweep_unclaimed(&mut game, PREMARKET_END + 1), CustomErrors::GameNotOver);
+
+        game.premarket_balance = 400;
+        game.sell_and_ref_balance = 200;
+        game.pool_balance = 1_000;
+        game.market_eggs = ENDGAME_LIMIT;
+        finalize_game(&mut game, PREMARKET_END + 1).unwrap();
+        let deadline = claim_deadline(&game).unwrap();
+        assert_eq!(deadline, PREMARKET_END + 1 + CLAIM_WINDOW);
+        assert_error(sweep_unclaimed(&mut game, deadline), CustomErrors::ClaimWindowOpen);
+
+        // Past the deadline the prize can no longer be claimed, only swept
+        assert_error(user_withdraw(&mut game, &mut player, deadline + 1), CustomErrors::ClaimWindowClosed);
+        let swept = sweep_unclaimed(&mut game, deadline + 1).unwrap();
+        assert_eq!(swept, VaultAmounts { dev: 0, premarket: 400, payables: 200, pool: 1_000 });
+        assert_eq!((game.premarket_balance, game.sell_and_ref_balance, game.pool_balance), (0, 0, 0));
+        assert_error(sweep_unclaimed(&mut game, deadline + 1), CustomErrors::AlreadySwept);
+
+        let dev_balance = game.dev_balance;
+        credit_sweep(&mut game, None, swept.total().unwrap()).unwrap();
+        assert_eq!(game.dev_balance, dev_balance + 1_600);
+    }
+
+    #[test]
+    fn sweep_credits_the_next_season_pool() {
+        let mut game = new_game();
+        game.sweep_destination = SweepDestination::NextSeason { authority: Pubkey::new_unique() };
+        assert_error(credit_sweep(&mut game, None, 500), CustomErrors::InvalidClaimPolicy);
+
+        let mut next_game = new_game();
+        let destination = credit_sweep(&mut game, Some(&mut next_game), 500).unwrap();
+        assert_eq!(destination, game.sweep_destination);
+        assert_eq!(next_game.pool_balance, 500);
+        assert_eq!(game.dev_balance, 0);
+    }
+
+    #[test]
+    fn sweep_falls_back_to_dev_vault_once_the_next_season_is_over() {
+        let mut game = new_game();
+        game.sweep_destination = SweepDestination::NextSeason { authority: Pubkey::new_unique() };
+        let mut next_game = new_game();
+        next_game.game_over = true;
+
+        let destination = credit_sweep(&mut game, Some(&mut next_game), 500).unwrap();
+        assert_eq!(destination, SweepDestination::DevVault);
+        assert_eq!(game.dev_balance, 500);
+        assert_eq!(next_game.pool_balance, 0);
+    }
+
+    #[test]
+    fn start_season_links_the_next_game() {
+        let previous_authority = Pubkey::new_unique();
+        let mut registry = GameRegistry { owner: Pubkey::new_unique(), season: 1, authority: previous_authority };
+        let mut previous = GameState { authority: previous_authority, season: 1, collection_key: Pubkey::new_unique(), ..new_game() };
+        let mut next = GameState { authority: Pubkey::new_unique(), ..new_game() };
+        let carry_over = SeasonCarryOver { treasury: true, bonuses: true };
+
+        // The current season has to be over first
+        assert_error(start_season(&mut registry, &mut previous, &mut next, carry_over), CustomErrors::GameNotOver);
+
+        previous.game_over = true;
+        start_season(&mut registry, &mut previous, &mut next, carry_over).unwrap();
+        assert_eq!((registry.season, registry.authority), (2, next.authority));
+        assert_eq!((next.season, next.previous_authority), (2, previous_authority));
+        assert_eq!(previous.sweep_destination, SweepDestination::NextSeason { authority: next.authority });
+        assert_eq!(next.collection_key, previous.collection_key);
+
+        // Season 1 is no longer current
+        let mut other = GameState { authority: Pubkey::new_unique(), ..new_game() };
+        assert_error(start_season(&mut registry, &mut previous, &mut other, carry_over), CustomErrors::NotCurrentSeason);
+    }
+
+    #[test]
+    fn carry_over_player_keeps_the_testnet_bonus() {
+        let previous_player = PlayerState { registered: true, testnet_player: true, shrimp: 100, ..Default::default() };
+        let mut game = new_game();
+        let mut player = PlayerState::default();
+        assert_error(carry_over_player(&game, &previous_player, &mut player), CustomErrors::CarryOverDisabled);
+
+        // Only the bonus comes over, not the production
+        game.carry_over = SeasonCarryOver { bonuses: true, ..Default::default() };
+        carry_over_player(&game, &previous_player, &mut player).unwrap();
+        assert!(player.testnet_player);
+        assert!(!player.registered);
+        assert_eq!(player.shrimp, 0);
+    }
+
+    #[test]
+    fn username_rules_set_characters_and_length() {
+        // The defaults keep 1-12 lowercase letters
+        let rules = UsernameRules::default();
+        assert!(check_username("shrimp", &rules).is_ok());
+        for name in ["", "thirteenchars", "shrimp1", "shrimp_farm", "Shrimp"] {
+            assert_error(check_username(name, &rules), CustomErrors::InvalidUsername);
+        }
+
+        let rules = UsernameRules { min_len: 3, max_len: 16, allow_digits: true, allow_underscore: true };
+        assert!(check_username("shrimp_farm_42", &rules).is_ok());
+        assert_error(check_username("ab", &rules), CustomErrors::InvalidUsername);
+        assert_error(check_username("shrimp-farm", &rules), CustomErrors::InvalidUsername);
+
+        // Limits must fit the profile
+        assert_error(check_username_rules(&UsernameRules { min_len: 0, ..rules }), CustomErrors::InvalidUsernameRules);
+        assert_error(check_username_rules(&UsernameRules { min_len: 17, ..rules }), CustomErrors::InvalidUsernameRules);
+        assert_error(check_username_rules(&UsernameRules { max_len: 21, ..rules }), CustomErrors::InvalidUsernameRules);
+    }
+
+    #[test]
+    fn pro_rata_scales_weights_past_u64() {
+        // Scaled shares round down by at most a few lamports
+        let total = u128::MAX / 3;
+        assert!((u64::MAX - 2..=u64::MAX).contains(&pro_rata(u64::MAX, total, total).unwrap()));
+        assert!((249..=250).contains(&pro_rata(1_000, total / 4, total).unwrap()));
+
+        // Small weights are exact
+        assert_eq!(pro_rata(1_000, 1, 4).unwrap(), 250);
+    }
+}
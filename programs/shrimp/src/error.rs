@@ -50,4 +50,60 @@ pub enum CustomErrors {
     InvalidReferrer,
     #[msg("Minted out")]
     MintedOut,
+    #[msg("Output below the requested minimum")]
+    SlippageExceeded,
+    #[msg("Transaction expired")]
+    TransactionExpired,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Treasury balance below the reserved balances")]
+    TreasuryUnderflow,
+    #[msg("Clock sysvar unavailable or invalid")]
+    ClockError,
+    #[msg("Malformed instructions sysvar")]
+    MalformedInstructionSysvar,
+    #[msg("Fee schedule exceeds the allowed bounds")]
+    InvalidFeeSchedule,
+    #[msg("Curve parameters are invalid or could overflow")]
+    InvalidCurveParams,
+    #[msg("Market has not reached the endgame limit")]
+    EndgameNotReached,
+    #[msg("Game is not over")]
+    GameNotOver,
+    #[msg("Claim window is still open")]
+    ClaimWindowOpen,
+    #[msg("Claim window is closed")]
+    ClaimWindowClosed,
+    #[msg("Invalid claim window or sweep destination")]
+    InvalidClaimPolicy,
+    #[msg("Unclaimed balances already swept")]
+    AlreadySwept,
+    #[msg("Game is not the current season")]
+    NotCurrentSeason,
+    #[msg("Season does not carry this over from the previous season")]
+    CarryOverDisabled,
+    #[msg("Username was changed too recently")]
+    UsernameCooldown,
+    #[msg("Username fee exceeds the allowed maximum")]
+    InvalidUsernameFee,
+    #[msg("Username is reserved")]
+    UsernameReserved,
+    #[msg("Username rules are invalid")]
+    InvalidUsernameRules,
+    #[msg("Referrer does not match the player's current referrer")]
+    ReferrerMismatch,
+    #[msg("Referrer cannot be changed yet")]
+    ReferrerLocked,
+    #[msg("Referral split exceeds the referral budget")]
+    InvalidReferralSplit,
+    #[msg("Referral tiers exceed the referral fee")]
+    InvalidReferralTiers,
+    #[msg("Referral guards are out of range")]
+    InvalidReferralGuards,
+    #[msg("Referral window is out of range")]
+    InvalidReferralWindow,
+    #[msg("Egg amount must be positive and at most the player's eggs")]
+    InvalidEggAmount,
+    #[msg("Sell share must be between 0 and 10000 basis points, exclusive")]
+    InvalidSellShare,
 }
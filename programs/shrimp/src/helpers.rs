@@ -1,4 +1,5 @@
 use crate::{error::*, state::*};
+use crate::engine::{ReferralAmounts, Referrer, Upline};
 use anchor_lang::prelude::*;
 use anchor_lang::{
     prelude::{AccountInfo, CpiContext, Program, Result},
@@ -9,57 +10,93 @@ use mpl_core::accounts::BaseAssetV1;
 use mpl_core::types::UpdateAuthority;
 use solana_program::serialize_utils::{read_pubkey, read_u16};
 
-pub fn calculate_trade(rt: u128, rs: u128, bs: u128) -> u128 {
-    let psn_bs = PSN.checked_mul(bs).unwrap();
-    let psnh_rt = PSNH.checked_mul(rt).unwrap();
-    let psn_rs = PSN.checked_mul(rs).unwrap();
+pub fn calculate_trade(rt: u128, rs: u128, bs: u128, curve: &CurveParams) -> Result<u128> {
+    let psn_bs = curve.psn.checked_mul(bs).ok_or(CustomErrors::MathOverflow)?;
+    let psnh_rt = curve.psnh.checked_mul(rt).ok_or(CustomErrors::MathOverflow)?;
+    let psn_rs = curve.psn.checked_mul(rs).ok_or(CustomErrors::MathOverflow)?;
 
-    let add_psn_rs_psnh_rt = psn_rs.checked_add(psnh_rt).unwrap();
-    let div_add_rt = add_psn_rs_psnh_rt.checked_div(rt).unwrap();
-    let add_psnd_psndh_rt = PSNH.checked_add(div_add_rt).unwrap();
+    let add_psn_rs_psnh_rt = psn_rs.checked_add(psnh_rt).ok_or(CustomErrors::MathOverflow)?;
+    let div_add_rt = add_psn_rs_psnh_rt.checked_div(rt).ok_or(CustomErrors::MathOverflow)?;
+    let add_psnd_psndh_rt = curve.psnh.checked_add(div_add_rt).ok_or(CustomErrors::MathOverflow)?;
 
-    return psn_bs.checked_div(add_psnd_psndh_rt).unwrap();
+    Ok(psn_bs.checked_div(add_psnd_psndh_rt).ok_or(CustomErrors::MathOverflow)?)
 }
 
-pub fn calculate_egg_buy(amount: u128, contract_balance: u128, market_eggs: u128) -> u128 {
-    let eggs: u128 = calculate_trade(amount, contract_balance, market_eggs);
-    let fee: u128 = (eggs.checked_mul(FEE.into()).unwrap())
+pub fn calculate_egg_buy(
+    amount: u128,
+    contract_balance: u128,
+    market_eggs: u128,
+    curve: &CurveParams,
+    fees: &FeeSchedule,
+) -> Result<u128> {
+    let eggs: u128 = calculate_trade(amount, contract_balance, market_eggs, curve)?;
+    let fee: u128 = (eggs.checked_mul(fees.trade_fee().into()).ok_or(CustomErrors::MathOverflow)?)
         .checked_div(100)
-        .unwrap();
-    return eggs.checked_sub(fee).unwrap();
+        .ok_or(CustomErrors::MathOverflow)?;
+    Ok(eggs.checked_sub(fee).ok_or(CustomErrors::MathOverflow)?)
 }
 
-pub fn calculate_egg_sell(eggs: u128, market_eggs: u128, contract_balance: u128) -> u128 {
-    let received: u128 = calculate_trade(eggs, market_eggs, contract_balance);
-    return received;
+pub fn calculate_egg_sell(eggs: u128, market_eggs: u128, contract_balance: u128, curve: &CurveParams) -> Result<u128> {
+    calculate_trade(eggs, market_eggs, contract_balance, curve)
 }
 
-pub fn get_eggs_since_last_hatch(player_state: &PlayerState, game_state: &GameState) -> u128 {
-    let now = Clock::get().unwrap().unix_timestamp as u128;
+// Current unix timestamp from the Clock sysvar
+pub fn current_timestamp() -> Result<u64> {
+    let clock = Clock::get().map_err(|_| error!(CustomErrors::ClockError))?;
+    u64::try_from(clock.unix_timestamp).map_err(|_| error!(CustomErrors::ClockError))
+}
+
+pub fn get_eggs_since_last_hatch(player_state: &PlayerState, game_state: &GameState) -> Result<u128> {
+    let now = current_timestamp()?;
+    get_eggs_since_last_hatch_at(player_state, game_state, now)
+}
+
+// Same as `get_eggs_since_last_hatch` with the current time supplied by the caller
+pub fn get_eggs_since_last_hatch_at(player_state: &PlayerState, game_state: &GameState, now: u64) -> Result<u128> {
     let last_interaction = if player_state.last_interaction == 0 {
         game_state.premarket_end
     } else {
         player_state.last_interaction
     };
-    let seconds_passed = now - last_interaction as u128;
-    return seconds_passed
-        .checked_mul(get_my_shrimp(player_state, game_state))
-        .unwrap();
+    let seconds_passed = now.checked_sub(last_interaction).ok_or(CustomErrors::ClockError)? as u128;
+    Ok(seconds_passed
+        .checked_mul(get_my_shrimp(player_state, game_state)?)
+        .ok_or(CustomErrors::MathOverflow)?)
+}
+
+pub fn get_my_eggs(player_state: &PlayerState, game_state: &GameState) -> Result<u128> {
+    let now = current_timestamp()?;
+    get_my_eggs_at(player_state, game_state, now)
+}
+
+pub fn get_my_eggs_at(player_state: &PlayerState, game_state: &GameState, now: u64) -> Result<u128> {
+    let new_eggs = get_eggs_since_last_hatch_at(player_state, game_state, now)?;
+    Ok(player_state.extra_eggs.checked_add(new_eggs).ok_or(CustomErrors::MathOverflow)?)
 }
 
-pub fn get_my_eggs(player_state: &PlayerState, game_state: &GameState) -> u128 {
-    return player_state.extra_eggs + get_eggs_since_last_hatch(player_state, game_state);
+// Shrimp owned by all players: bought and hatched shrimp plus every premarket buyer's shrimp
+pub fn get_total_shrimp(game_state: &GameState) -> Result<u128> {
+    let premarket_shrimp = if game_state.premarket_spent > 0 {
+        calculate_egg_buy(game_state.premarket_spent as u128, 0, game_state.curve.market_start, &game_state.curve, &game_state.fees)?
+    } else {
+        0
+    };
+
+    Ok(game_state
+        .total_shrimp
+        .checked_add(premarket_shrimp / game_state.curve.eggs_to_hatch_1shrimp)
+        .ok_or(CustomErrors::MathOverflow)?)
 }
 
-pub fn get_my_shrimp(player_state: &PlayerState, game_state: &GameState) -> u128 {
+pub fn get_my_shrimp(player_state: &PlayerState, game_state: &GameState) -> Result<u128> {
     // Calculate the player's share of the premarket spending
     let player_share = if game_state.premarket_spent > 0 {
         player_state
             .premarket_spent
             .checked_mul(100000)
-            .unwrap()
+            .ok_or(CustomErrors::MathOverflow)?
             .checked_div(game_state.premarket_spent)
-            .unwrap()
+            .ok_or(CustomErrors::MathOverflow)?
     } else {
         0
     };
@@ -67,21 +104,218 @@ pub fn get_my_shrimp(player_state: &PlayerState, game_state: &GameState) -> u128
     // Calculate the amount of shrimp that would be purchased with the player's share of premarket spending
     let premarket_shrimp = if player_share > 0 {
         let shrimp_for_premarket_spent =
-            calculate_egg_buy(game_state.premarket_spent as u128, 0, MARKET_START);
+            calculate_egg_buy(game_state.premarket_spent as u128, 0, game_state.curve.market_start, &game_state.curve, &game_state.fees)?;
         shrimp_for_premarket_spent
             .checked_mul(player_share as u128)
-            .unwrap()
+            .ok_or(CustomErrors::MathOverflow)?
             .checked_div(100000)
-            .unwrap()
+            .ok_or(CustomErrors::MathOverflow)?
     } else {
         0
     };
 
     // Calculate and return the total shrimp count without modifying player_state
-    player_state
+    Ok(player_state
         .shrimp
-        .checked_add(premarket_shrimp.checked_div(EGGS_TO_HATCH_1SHRIMP).unwrap())
-        .unwrap()
+        .checked_add(premarket_shrimp / game_state.curve.eggs_to_hatch_1shrimp)
+        .ok_or(CustomErrors::MathOverflow)?)
+}
+
+// Returns (dev_fee, premarket_fee) for a buy or sell amount
+pub fn calculate_fees(amount: u64, fees: &FeeSchedule) -> Result<(u64, u64)> {
+    let dev_fee = amount.checked_mul(fees.dev_fee).ok_or(CustomErrors::MathOverflow)? / 100;
+    let premarket_fee = amount.checked_mul(fees.premarket_fee).ok_or(CustomErrors::MathOverflow)? / 100;
+    Ok((dev_fee, premarket_fee))
+}
+
+// Production bonus percent for the player: NFT holders and testnet players
+pub fn get_bonus_percent(
+    game_state: &GameState,
+    player_state: &PlayerState,
+    maybe_nft_asset: &Option<Account<BaseAssetV1>>,
+    player: Pubkey,
+) -> Result<u128> {
+    let mut bonus_percent: u128 = 0;
+
+    // NFT bonus only applies once the collection is set
+    if !game_state.collection_key.to_string().eq("11111111111111111111111111111111")
+        && is_nft_holder(maybe_nft_asset, player, game_state.collection_key)?
+    {
+        bonus_percent += game_state.fees.nft_bonus;
+    }
+
+    // Add extra 1% bonus for testnet players
+    if player_state.testnet_player {
+        bonus_percent += game_state.fees.testnet_bonus;
+    }
+
+    Ok(bonus_percent)
+}
+
+pub fn apply_bonus(eggs: u128, bonus_percent: u128) -> Result<u128> {
+    if bonus_percent == 0 {
+        return Ok(eggs);
+    }
+    Ok(eggs.checked_mul(100 + bonus_percent).ok_or(CustomErrors::MathOverflow)? / 100)
+}
+
+// Reject fee schedules outside the on-chain bounds
+pub fn check_fee_schedule(fees: &FeeSchedule) -> Result<()> {
+    require!(
+        fees.dev_fee.checked_add(fees.premarket_fee).is_some_and(|fee| fee <= MAX_FEE),
+        CustomErrors::InvalidFeeSchedule
+    );
+    require!(
+        fees.referral_fee.checked_add(fees.referral_cashback).is_some_and(|fee| fee <= MAX_REFERRAL),
+        CustomErrors::InvalidFeeSchedule
+    );
+    require!(
+        fees.nft_bonus <= MAX_BONUS && fees.testnet_bonus <= MAX_BONUS,
+        CustomErrors::InvalidFeeSchedule
+    );
+    Ok(())
+}
+
+// A referrer's cashback must give back at least the game's cashback and stay within the referral budget
+pub fn check_referral_split(fees: &FeeSchedule, cashback: u64) -> Result<()> {
+    require!(
+        cashback >= fees.referral_cashback && cashback <= fees.referral_fee.saturating_add(fees.referral_cashback),
+        CustomErrors::InvalidReferralSplit
+    );
+    Ok(())
+}
+
+// Referral fee and cashback percents of a buy, following the referrer's split of
+// the referral budget left after `tier_percent` is paid to the upline
+pub fn referral_split(fees: &FeeSchedule, referrer_state: &PlayerState, tier_percent: u64) -> (u64, u64) {
+    let budget = fees.referral_fee.saturating_add(fees.referral_cashback).saturating_sub(tier_percent);
+    let cashback = referrer_state.referee_cashback.max(fees.referral_cashback).min(budget);
+    (budget - cashback, cashback)
+}
+
+// Tiers are paid out of the referrer's fee, so the game's cashback is always covered
+pub fn check_referral_tiers(fees: &FeeSchedule, tiers: [u64; 2]) -> Result<()> {
+    require!(
+        tiers[0].checked_add(tiers[1]).is_some_and(|tiers| tiers <= fees.referral_fee),
+        CustomErrors::InvalidReferralTiers
+    );
+    Ok(())
+}
+
+// Reject referrer stake requirements above the maximum and caps below the referral
+// fee of a minimum buy (lower the referral fee instead to stop paying referrers)
+pub fn check_referral_guards(fees: &FeeSchedule, guards: &ReferralGuards) -> Result<()> {
+    let min_cap = MIN_BUY.saturating_mul(fees.referral_fee) / 100;
+    require!(
+        guards.min_referrer_spend <= MAX_MIN_REFERRER_SPEND
+            && (guards.referee_cap == 0 || guards.referee_cap >= min_cap),
+        CustomErrors::InvalidReferralGuards
+    );
+    Ok(())
+}
+
+// Referral windows must end, within a year, and last at least the minimum outside test environments
+pub fn check_referral_window(game_state: &GameState, referral_window: u64) -> Result<()> {
+    require!(
+        referral_window > 0
+            && referral_window <= MAX_REFERRAL_WINDOW
+            && (game_state.test_env || referral_window >= MIN_REFERRAL_WINDOW),
+        CustomErrors::InvalidReferralWindow
+    );
+    Ok(())
+}
+
+// Reject curves that are degenerate or could overflow `calculate_trade`
+pub fn check_curve_params(curve: &CurveParams) -> Result<()> {
+    require!(
+        curve.psn > 0 && curve.psnh > 0 && curve.eggs_to_hatch_1shrimp > 0,
+        CustomErrors::InvalidCurveParams
+    );
+    require!(
+        curve.market_start > 0 && curve.market_start < curve.endgame_limit,
+        CustomErrors::InvalidCurveParams
+    );
+
+    // Trade inputs are lamports (u64) or market eggs (at most the endgame limit),
+    // so (psn + psnh) times the larger of the two must fit in u128
+    let max_input = curve.endgame_limit.max(u64::MAX as u128);
+    let max_product = curve.psn
+        .checked_add(curve.psnh)
+        .and_then(|weight| weight.checked_mul(max_input));
+    require!(max_product.is_some(), CustomErrors::InvalidCurveParams);
+    Ok(())
+}
+
+// Username length and characters must follow the username rules
+pub fn check_username(username: &str, rules: &UsernameRules) -> Result<()> {
+    let allowed = |c: char| {
+        c.is_ascii_lowercase()
+            || (rules.allow_digits && c.is_ascii_digit())
+            || (rules.allow_underscore && c == '_')
+    };
+    require!(
+        username.len() >= rules.min_len as usize
+            && username.len() <= rules.max_len as usize
+            && username.chars().all(allowed),
+        CustomErrors::InvalidUsername
+    );
+    Ok(())
+}
+
+// A username can be claimed only while it is not reserved
+pub fn check_not_reserved(reserved_name: &AccountInfo) -> Result<()> {
+    require!(
+        reserved_name.data_is_empty(),
+        CustomErrors::UsernameReserved
+    );
+    Ok(())
+}
+
+// Reject empty or out of range length limits
+pub fn check_username_rules(rules: &UsernameRules) -> Result<()> {
+    require!(
+        rules.min_len >= 1
+            && rules.min_len <= rules.max_len
+            && rules.max_len as usize <= PlayerProfile::MAX_USERNAME_LENGTH,
+        CustomErrors::InvalidUsernameRules
+    );
+    Ok(())
+}
+
+// A username can be changed, or claimed again after a release, once the cooldown has passed
+pub fn check_username_cooldown(profile: &PlayerProfile, now: u64) -> Result<()> {
+    require!(
+        profile.name_changed_at == 0 || now >= profile.name_changed_at.saturating_add(USERNAME_COOLDOWN),
+        CustomErrors::UsernameCooldown
+    );
+    Ok(())
+}
+
+// Reject claim windows below the minimum (outside test environments) and sweeps into the game itself
+pub fn check_claim_policy(game_state: &GameState, claim_window: u64, destination: &SweepDestination) -> Result<()> {
+    require!(
+        game_state.test_env || claim_window >= MIN_CLAIM_WINDOW,
+        CustomErrors::InvalidClaimPolicy
+    );
+    if let SweepDestination::NextSeason { authority } = destination {
+        require!(*authority != game_state.authority, CustomErrors::InvalidClaimPolicy);
+    }
+    Ok(())
+}
+
+// Reject an account that is not the program address derived from `seeds`
+pub fn check_pda(key: Pubkey, seeds: &[&[u8]], error: CustomErrors) -> Result<()> {
+    let (expected, _) = Pubkey::find_program_address(seeds, &crate::ID);
+    require_keys_eq!(key, expected, error);
+    Ok(())
+}
+
+// Reject orders that land after the client supplied deadline
+pub fn check_deadline(valid_until: Option<u64>, now: u64) -> Result<()> {
+    if let Some(valid_until) = valid_until {
+        require!(now <= valid_until, CustomErrors::TransactionExpired);
+    }
+    Ok(())
 }
 
 // transfer lamports from on person to another without using pda signer
@@ -91,6 +325,11 @@ pub fn transfer_lamports<'a>(
     system_program: &Program<'a, System>,
     lamports: u64,
 ) -> Result<()> {
+    // Nothing to move
+    if lamports == 0 {
+        return Ok(());
+    }
+
     let cpi_accounts = SolanaTransfer {
         from: from.to_account_info(),
         to: to.to_account_info(),
@@ -104,14 +343,55 @@ pub fn transfer_lamports<'a>(
     Ok(())
 }
 
-// transfer lamports from an owned PDA to another account
-pub fn transfer_lamports_from_owned_pda<'a>(
-    from: &AccountInfo<'a>,
+// a player is registered once their global profile holds a username
+pub fn is_registered(profile: &AccountInfo) -> bool {
+    if profile.owner != &crate::ID || profile.data_is_empty() {
+        return false;
+    }
+    let data = profile.try_borrow_data();
+    data.ok()
+        .and_then(|data| PlayerProfile::try_deserialize(&mut &data[..]).ok())
+        .is_some_and(|profile| !profile.username.is_empty())
+}
+
+// fund each vault up to its rent so the balances held above it can be any amount
+pub fn fund_vaults<'a>(
+    payer: &AccountInfo<'a>,
+    vaults: [&AccountInfo<'a>; 4],
+    system_program: &Program<'a, System>,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(0);
+    for vault in vaults {
+        transfer_lamports(payer, vault, system_program, rent.saturating_sub(vault.lamports()))?;
+    }
+    Ok(())
+}
+
+// transfer lamports out of a system-owned vault PDA, signing with its seeds
+pub fn transfer_lamports_from_vault<'a>(
+    vault: &AccountInfo<'a>,
     to: &AccountInfo<'a>,
+    system_program: &Program<'a, System>,
+    seed: &[u8],
+    authority: &Pubkey,
+    bump: u8,
     lamports: u64,
 ) -> Result<()> {
-    **from.try_borrow_mut_lamports()? -= lamports;
-    **to.try_borrow_mut_lamports()? += lamports;
+    // Nothing to move
+    if lamports == 0 {
+        return Ok(());
+    }
+
+    let cpi_accounts = SolanaTransfer {
+        from: vault.to_account_info(),
+        to: to.to_account_info(),
+    };
+    let cpi_program = system_program.to_account_info();
+    let signer_seeds: &[&[&[u8]]] = &[&[seed, authority.as_ref(), &[bump]]];
+
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+    system_program::transfer(cpi_context, lamports)?;
 
     Ok(())
 }
@@ -120,61 +400,137 @@ pub fn process_referral(
     game_state: &mut GameState,
     player_state: &mut PlayerState,
     player: Pubkey,
-    referrer_state: &mut PlayerState,
-    referrer: Pubkey,
+    referrer: Referrer,
     amount: u64,
-    payer: Pubkey
-) -> Result<(u64, u64)> {
+    payer: Pubkey,
+    now: u64,
+) -> Result<ReferralAmounts> {
+    let Referrer { key: referrer, state: referrer_state, stats, registered, upline } = referrer;
+
     // Do nothing for no referrer
     if referrer == pubkey!("11111111111111111111111111111111") {
-        return Ok((0, 0))
+        return Ok(ReferralAmounts::default())
     }
 
-    // Referrer must be a registered player
-    require!(referrer_state.registered, CustomErrors::InvalidReferrer);
+    // Referrer must have a username in the global profile registry
+    require!(registered, CustomErrors::InvalidReferrer);
 
     // Can't be self
     require!(!referrer.eq(&player), CustomErrors::InvalidReferrer);
 
-    // Only update the referrer for new player
-    if player.eq(&payer) || 
-        (player_state.current_referrer == pubkey!("11111111111111111111111111111111") && 
-            player_state.premarket_spent == 0 && 
-            player_state.market_spent == 0) {
+    // A buy only sets the referrer of a player without one, on their own or their first purchase
+    if player_state.current_referrer == pubkey!("11111111111111111111111111111111") &&
+        (player.eq(&payer) || (player_state.premarket_spent == 0 && player_state.market_spent == 0)) {
             player_state.current_referrer = referrer;
+            player_state.referrer_set_at = now;
+            stats.referees = stats.referees.saturating_add(1);
+    }
+
+    // Later changes go through `set_referrer`
+    require!(player_state.current_referrer == referrer, CustomErrors::ReferrerMismatch);
+
+    // Attribution runs from the first referred buy until the game's window closes,
+    // after which the whole referral budget stays in the pool
+    if player_state.referred_at == 0 {
+        player_state.referred_at = now;
+    }
+    if game_state.referral_window > 0 && now >= player_state.referred_at.saturating_add(game_state.referral_window) {
+        let expired = !player_state.referral_expired;
+        player_state.referral_expired = true;
+        return Ok(ReferralAmounts { expired, ..Default::default() })
     }
 
-    // Returns (ref_fee, cashback)
-    let ref_fee;
-    let cashback;
+    // Referrers without a stake in the game earn nothing, and neither does their
+    // upline: their share of the budget stays in the pool
+    let guards = game_state.referral_guards;
+    let staked = referrer_state.premarket_spent.saturating_add(referrer_state.market_spent) >= guards.min_referrer_spend;
+
+    // Pay the referrer's upline their tier share, out of the referral budget. The
+    // chain follows `current_referrer` from the referrer, so a tier that is owed a
+    // share must be passed; the buyer and the referrer are never paid twice and
+    // their states are not passed again.
+    let mut tier_fees = [0; 2];
+    let mut tier_percent = 0;
+    let mut upline_key = referrer_state.current_referrer;
+    for (tier, upline) in upline.into_iter().enumerate() {
+        let expected = upline_key;
+        let in_buy = expected == player || expected == referrer;
+        let Some(Upline { key, state }) = upline else {
+            let owed = staked && game_state.referral_tiers[tier..].iter().any(|share| *share > 0);
+            require!(
+                expected == pubkey!("11111111111111111111111111111111") || in_buy || !owed,
+                CustomErrors::InvalidReferrer
+            );
+            upline_key = if expected == player {
+                player_state.current_referrer
+            } else if expected == referrer {
+                referrer_state.current_referrer
+            } else {
+                pubkey!("11111111111111111111111111111111")
+            };
+            continue;
+        };
+        require!(
+            expected != pubkey!("11111111111111111111111111111111") && !in_buy && key == expected,
+            CustomErrors::InvalidReferrer
+        );
+        upline_key = state.current_referrer;
+        if !staked {
+            continue;
+        }
 
-    // Calculate referral fee and cashback
-    ref_fee = amount
-        .checked_mul(REFERRAL_FEE)
+        let share = game_state.referral_tiers[tier];
+        tier_fees[tier] = amount
+            .checked_mul(share)
+            .unwrap_or_default()
+            .checked_div(100)
+            .unwrap_or_default();
+        tier_percent += share;
+        state.tier_total = state.tier_total.checked_add(tier_fees[tier]).ok_or(CustomErrors::MathOverflow)?;
+    }
+
+    // Calculate referral fee and cashback with the referrer's split of the rest
+    let (fee_percent, cashback_percent) = referral_split(&game_state.fees, referrer_state, tier_percent);
+    let mut ref_fee = amount
+        .checked_mul(fee_percent)
         .unwrap_or_default()
         .checked_div(100)
         .unwrap_or_default();
-    cashback = amount
-        .checked_mul(REFERRAL_CASHBACK)
+    let cashback = amount
+        .checked_mul(cashback_percent)
         .unwrap_or_default()
         .checked_div(100)
         .unwrap_or_default();
 
+    // Cap what the referrer earns from this referee, the rest stays in the pool
+    if !staked {
+        ref_fee = 0;
+    } else if guards.referee_cap > 0 {
+        ref_fee = ref_fee.min(guards.referee_cap.saturating_sub(player_state.referrer_earned));
+    }
+    player_state.referrer_earned = player_state.referrer_earned.checked_add(ref_fee).ok_or(CustomErrors::MathOverflow)?;
+
     // Update referrer's total with their fee
-    referrer_state.referral_total = referrer_state.referral_total.checked_add(ref_fee).unwrap();
+    referrer_state.referral_total = referrer_state.referral_total.checked_add(ref_fee).ok_or(CustomErrors::MathOverflow)?;
 
     // Update buyer's referral total with their cashback
-    player_state.referral_total = player_state.referral_total.checked_add(cashback).unwrap();
+    player_state.referral_total = player_state.referral_total.checked_add(cashback).ok_or(CustomErrors::MathOverflow)?;
+    player_state.cashback_total = player_state.cashback_total.checked_add(cashback).ok_or(CustomErrors::MathOverflow)?;
+
+    // Update the referrer's statistics
+    stats.referrer = referrer;
+    stats.referred_volume = stats.referred_volume.checked_add(amount).ok_or(CustomErrors::MathOverflow)?;
+    stats.fees_earned = stats.fees_earned.checked_add(ref_fee).ok_or(CustomErrors::MathOverflow)?;
+    stats.referee_cashback = stats.referee_cashback.checked_add(cashback).ok_or(CustomErrors::MathOverflow)?;
 
-    // Add both ref fee and cashback to sell_and_ref_balance for tracking
+    // Add the ref fee, cashback and tier fees to sell_and_ref_balance for tracking
+    let referral = ReferralAmounts { referral_fee: ref_fee, cashback, tier_fees, expired: false };
     game_state.sell_and_ref_balance = game_state
         .sell_and_ref_balance
-        .checked_add(ref_fee)
-        .unwrap()
-        .checked_add(cashback)
-        .unwrap();
+        .checked_add(referral.total()?)
+        .ok_or(CustomErrors::MathOverflow)?;
 
-    Ok((ref_fee, cashback))
+    Ok(referral)
 }
 
 pub fn is_nft_holder(
@@ -222,7 +578,7 @@ pub fn limit_instructions(
     let mut index = 0;
 
     let num_instructions =
-        read_u16(&mut index, &sysvar_data).map_err(|_| ProgramError::InvalidAccountData)?;
+        read_u16(&mut index, &sysvar_data).map_err(|_| error!(CustomErrors::MalformedInstructionSysvar))?;
 
     if num_instructions > max_ixs as u16 {
         msg!("Transaction had {} instructions", num_instructions);
@@ -238,12 +594,12 @@ pub fn limit_instructions(
         let mut offset = 2 + (index * 2) as usize;
 
         // offset for the number of accounts
-        offset = read_u16(&mut offset, &sysvar_data).unwrap() as usize;
-        let num_accounts = read_u16(&mut offset, &sysvar_data).unwrap();
+        offset = read_u16(&mut offset, &sysvar_data).map_err(|_| error!(CustomErrors::MalformedInstructionSysvar))? as usize;
+        let num_accounts = read_u16(&mut offset, &sysvar_data).map_err(|_| error!(CustomErrors::MalformedInstructionSysvar))?;
 
         // offset for the program id
         offset += (num_accounts as usize) * (1 + 32);
-        let program_id = read_pubkey(&mut offset, &sysvar_data).unwrap();
+        let program_id = read_pubkey(&mut offset, &sysvar_data).map_err(|_| error!(CustomErrors::MalformedInstructionSysvar))?;
 
         for program in &programs {
             if program_id == *program {
@@ -255,5 +611,5 @@ pub fn limit_instructions(
         return err!(CustomErrors::BadInstruction);
     }
 
-    Ok({})
+    Ok(())
 }
@@ -1,4 +1,5 @@
 
+use crate::helpers::current_timestamp;
 use crate::{error::*};
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::{
@@ -6,7 +7,6 @@ use anchor_lang::solana_program::{
     program::invoke_signed,
     sysvar
 };
-use anchor_lang::solana_program::clock;
 use crate::state::{GameState, MinterState, CANDY_MACHINE_AUTHORITY_SEED};
 
 /// The mint asset discriminator for candy machine
@@ -149,7 +149,7 @@ pub fn admin_mint(ctx: Context<AdminMint>) -> Result<()> {
     ctx.accounts.game_state.nfts_minted += 1;
 
     // Emit an event with the updated total supply (nfts_minted)
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+    let now: u64 = current_timestamp()?;
     emit!(AdminMinted {
         event_index: ctx.accounts.game_state.event_index,
         player: ctx.accounts.player.key(),
@@ -159,7 +159,7 @@ pub fn admin_mint(ctx: Context<AdminMint>) -> Result<()> {
 
     // Update indexes
     ctx.accounts.game_state.event_index =
-        ctx.accounts.game_state.event_index.checked_add(1).unwrap();
+        ctx.accounts.game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
 
     Ok(())
 }
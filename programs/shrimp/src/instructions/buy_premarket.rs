@@ -1,89 +1,113 @@
-use crate::account::BuyAccounts;
-use crate::{error::*, state::*};
+use crate::account::{BuyAccounts, BuyParts};
+use crate::error::*;
+use crate::engine::{self, Buyer};
 use crate::helpers::*;
+use crate::instructions::{ReferralCredited, ReferralExpired};
 use anchor_lang::prelude::*;
 use solana_program::pubkey::Pubkey;
-use anchor_lang::solana_program::clock;
 
 pub fn buy_premarket(
     ctx: Context<BuyAccounts>,
     amount: u64, 
+    valid_until: Option<u64>,
+) -> Result<()> {
+    apply_buy_premarket(ctx.accounts.parts()?, amount, valid_until)
+}
+
+/// Premarket buy rules, transfers and event shared by `buy_premarket` and `buy_premarket_by_username`
+pub fn apply_buy_premarket(
+    accounts: BuyParts,
+    amount: u64, 
+    valid_until: Option<u64>,
 ) -> Result<()> {
     // Check transaction restrictions
-    limit_instructions(&ctx.accounts.sysvar_instructions, ctx.accounts.game_state.max_ixs, &ctx.accounts.game_state.program_whitelist).unwrap();
+    limit_instructions(accounts.sysvar_instructions, accounts.game_state.max_ixs, &accounts.game_state.program_whitelist)?;
 
-    // Check amount
-    require!(
-        amount >= MIN_BUY,
-        CustomErrors::BuyAmountTooLow
-    );
+    // Reject the order if it landed after its deadline
+    let now: u64 = current_timestamp()?;
+    check_deadline(valid_until, now)?;
 
-    // Get state
-    let player_state = &mut ctx.accounts.player_state;
-    let game_state = &mut ctx.accounts.game_state;
-    let referrer = &ctx.accounts.referrer;
+    // Apply the premarket buy rules to the player, the game and the referrer
+    let buyer = Buyer {
+        key: accounts.player.key(),
+        payer: accounts.payer.key(),
+        state: accounts.player_state,
+        referrer: accounts.referrer,
+    };
+    let effects = engine::buy_premarket(accounts.game_state, buyer, amount, now)?;
 
-    // Check game is in premarket
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-    require!(
-        now < game_state.premarket_end,
-        CustomErrors::PreMarketOver
-    );
+    let player_state = &accounts.player_state;
+    let game_state = accounts.game_state;
 
-    // Transfer SOL from player to treasury
+    // Transfer the dev fee from player to the dev vault
     transfer_lamports(
-        &ctx.accounts.player,
-        &game_state.to_account_info(),
-        &ctx.accounts.system_program,
-        amount,
+        accounts.player,
+        accounts.dev_vault,
+        accounts.system_program,
+        effects.deposits.dev,
     )?;
 
-    // Handle referrals
-    let referrer_key = referrer.as_ref().map(|x| x.key());
-    if let Some(referrer_pubkey) = referrer_key {
-        let referrer_state = &mut ctx.accounts.referrer_state.as_mut().unwrap();
-        let (_,_) = process_referral(
-            game_state,
-            player_state,
-            ctx.accounts.player.key(),
-            referrer_state,
-            referrer_pubkey,
-            amount,
-            ctx.accounts.payer.key(),
-        )?;
-    }
-
-    // Update player and game state
-    game_state.premarket_spent = game_state.premarket_spent.checked_add(amount).unwrap();
-    player_state.premarket_spent = player_state.premarket_spent.checked_add(amount).unwrap();
+    // Transfer the referral fee and cashback to the payables vault
+    transfer_lamports(
+        accounts.player,
+        accounts.payables_vault,
+        accounts.system_program,
+        effects.deposits.payables,
+    )?;
 
-    // Add dev fee to dev balance
-    game_state.dev_balance = game_state
-        .dev_balance
-        .checked_add(amount.checked_mul(DEV_FEE).unwrap().checked_div(100).unwrap())
-        .unwrap();
+    // Transfer the rest to the pool vault
+    transfer_lamports(
+        accounts.player,
+        accounts.pool_vault,
+        accounts.system_program,
+        effects.deposits.pool,
+    )?;
 
-    // Recalculate the game balance from treasury after subtracting reserved balances
-    let treasury_lamports = **game_state.to_account_info().lamports.borrow();
-    let game_balance = treasury_lamports
-        .checked_sub(game_state.sell_and_ref_balance).unwrap()
-        .checked_sub(game_state.dev_balance).unwrap()
-        .checked_sub(game_state.premarket_balance).unwrap();
+    // Game balance after the buy
+    let game_balance = game_state.pool_balance;
 
     // Emit event
     emit!(PreMarketBuy {
         game_index:  game_state.game_index,
         event_index: game_state.event_index,
-        player: ctx.accounts.player.key(),
+        player: accounts.player.key(),
         referrer:    player_state.current_referrer,
         game_balance,
         sol_amount: amount,
-        timestamp: clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
+        timestamp: now,
     });
 
     // Update indexes
-    game_state.event_index = game_state.event_index.checked_add(1).unwrap();
-    game_state.game_index  = game_state.game_index.checked_add(1).unwrap();
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    game_state.game_index  = game_state.game_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    // Emit an event for the referral fee and cashback
+    let referral = effects.referral;
+    if referral.total()? > 0 {
+        emit!(ReferralCredited {
+            event_index: game_state.event_index,
+            player: accounts.player.key(),
+            referrer: player_state.current_referrer,
+            sol_amount: amount,
+            referral_fee: referral.referral_fee,
+            cashback: referral.cashback,
+            tier_fees: referral.tier_fees,
+            timestamp: now,
+        });
+        game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    }
+
+    // Emit an event once the referrer's attribution window has closed
+    if referral.expired {
+        emit!(ReferralExpired {
+            event_index: game_state.event_index,
+            player: accounts.player.key(),
+            referrer: player_state.current_referrer,
+            referred_at: player_state.referred_at,
+            timestamp: now,
+        });
+        game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    }
 
     Ok(())
 }
@@ -98,7 +122,7 @@ pub struct PreMarketBuy {
     pub player: Pubkey,
     /// Will be `Pubkey::default()` (all 1s) if none is set or if the buyer referred themselves.
     pub referrer: Pubkey,
-    /// The current game balance (after subtracting reserved amounts).
+    /// The pool balance after this event.
     pub game_balance: u64,
     /// The SOL amount involved in the event (spent in a buy).
     pub sol_amount: u64,
@@ -0,0 +1,13 @@
+use crate::account::BuyByUsernameAccounts;
+use crate::instructions::apply_buy_premarket;
+use anchor_lang::prelude::*;
+
+pub fn buy_premarket_by_username(
+    ctx: Context<BuyByUsernameAccounts>,
+    _username: String,
+    amount: u64, 
+    valid_until: Option<u64>,
+) -> Result<()> {
+    // The referrer was resolved from their username by the account constraints
+    apply_buy_premarket(ctx.accounts.parts()?, amount, valid_until)
+}
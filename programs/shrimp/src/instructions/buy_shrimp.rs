@@ -1,124 +1,125 @@
-use crate::account::BuyAccounts;
-use crate::{error::*, state::*};
+use crate::account::{BuyAccounts, BuyParts};
+use crate::error::*;
+use crate::engine::{self, Buyer};
 use crate::helpers::*;
 use anchor_lang::prelude::*;
 use solana_program::pubkey::Pubkey;
-use anchor_lang::solana_program::clock;
 
 pub fn buy_shrimp(
     ctx: Context<BuyAccounts>,
     amount: u64, 
+    min_shrimp_out: Option<u128>,
+    valid_until: Option<u64>,
 ) -> Result<()> {
-    // Check transaction restrictions
-    limit_instructions(&ctx.accounts.sysvar_instructions, ctx.accounts.game_state.max_ixs, &ctx.accounts.game_state.program_whitelist).unwrap();
-
-    // Check amount
-    require!(
-        amount >= MIN_BUY,
-        CustomErrors::BuyAmountTooLow
-    );
-
-    let player_state = &mut ctx.accounts.player_state;
-    let game_state = &mut ctx.accounts.game_state;
-    let referrer = &ctx.accounts.referrer;
-
-    // Check game is out of premarket
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-    require!(
-        now > game_state.premarket_end,
-        CustomErrors::PreMarketInProgress
-    );
-
-    // Ensure the game is not over already
-    require!(
-        !game_state.game_over,
-        CustomErrors::GameOver
-    );
-
-    // Calculate game balance from treasury
-    let treasury_lamports = **game_state.to_account_info().lamports.borrow();
-    let game_balance = treasury_lamports
-        .checked_sub(game_state.sell_and_ref_balance).unwrap()        
-        .checked_sub(game_state.dev_balance).unwrap()
-        .checked_sub(game_state.premarket_balance).unwrap();
-        
-    // Calculate eggs bought
-    let eggs_bought: u128 = calculate_egg_buy(amount as u128, game_balance as u128, game_state.market_eggs);
-
-    // Calculate current eggs and add them to extra_eggs if present
-    let new_eggs = get_eggs_since_last_hatch(player_state, game_state);
-    if new_eggs > 0 {
-        player_state.extra_eggs = player_state.extra_eggs.checked_add(new_eggs).unwrap_or(player_state.extra_eggs);
-    }
+    apply_buy_shrimp(ctx.accounts.parts()?, amount, min_shrimp_out, valid_until)
+}
 
-    // Transfer SOL to the treasury
+/// Buy rules, transfers and event shared by `buy_shrimp` and `buy_shrimp_by_username`
+pub fn apply_buy_shrimp(
+    accounts: BuyParts,
+    amount: u64, 
+    min_shrimp_out: Option<u128>,
+    valid_until: Option<u64>,
+) -> Result<()> {
+    // Check transaction restrictions
+    limit_instructions(accounts.sysvar_instructions, accounts.game_state.max_ixs, &accounts.game_state.program_whitelist)?;
+
+    // Reject the order if it landed after its deadline
+    let now: u64 = current_timestamp()?;
+    check_deadline(valid_until, now)?;
+
+    // Apply the buy rules to the player, the game and the referrer
+    let min_shrimp_out = min_shrimp_out.unwrap_or_default();
+    let buyer = Buyer {
+        key: accounts.player.key(),
+        payer: accounts.payer.key(),
+        state: accounts.player_state,
+        referrer: accounts.referrer,
+    };
+    let effects = engine::buy_shrimp(accounts.game_state, buyer, amount, min_shrimp_out, now)?;
+    let shrimp_to_add = effects.quote.shrimp;
+
+    let player_state = &accounts.player_state;
+    let game_state = accounts.game_state;
+
+    // Transfer the fees to the dev and premarket vaults
+    transfer_lamports(
+        accounts.payer,
+        accounts.dev_vault,
+        accounts.system_program,
+        effects.deposits.dev,
+    )?;
     transfer_lamports(
-        &ctx.accounts.payer,
-        &game_state.to_account_info(),
-        &ctx.accounts.system_program,
-        amount
+        accounts.payer,
+        accounts.premarket_vault,
+        accounts.system_program,
+        effects.deposits.premarket,
     )?;
 
-    // Add dev fee to dev balance
-    game_state.dev_balance = game_state.dev_balance
-        .checked_add(amount.checked_mul(DEV_FEE).unwrap().checked_div(100).unwrap())
-        .unwrap();
-
-    // Add to premarket balance
-    let premarket_fee = amount.checked_mul(PREMARKET_FEE).unwrap().checked_div(100).unwrap();
-    game_state.premarket_earned = game_state.premarket_earned
-        .checked_add(premarket_fee)
-        .unwrap();
-    game_state.premarket_balance = game_state.premarket_balance
-        .checked_add(premarket_fee)
-        .unwrap();
-
-    // Convert eggs to shrimp
-    let shrimp_to_add = eggs_bought.checked_div(EGGS_TO_HATCH_1SHRIMP).unwrap_or_default();
-    player_state.shrimp = player_state.shrimp.checked_add(shrimp_to_add).unwrap_or(player_state.shrimp);
-
-    // Handle referrals
-    let referrer_key = referrer.as_ref().map(|x| x.key());
-    if let Some(referrer_pubkey) = referrer_key {
-        let referrer_state = &mut ctx.accounts.referrer_state.as_mut().unwrap();
-        let (_, _) = process_referral(
-            game_state,
-            player_state,
-            ctx.accounts.player.key(),
-            referrer_state,
-            referrer_pubkey,
-            amount,
-            ctx.accounts.payer.key(),
-        )?;
-    }
+    // Transfer the referral fee and cashback to the payables vault
+    transfer_lamports(
+        accounts.payer,
+        accounts.payables_vault,
+        accounts.system_program,
+        effects.deposits.payables,
+    )?;
 
-    // Update last interaction and market spend
-    player_state.last_interaction = now;
-    player_state.market_spent = player_state.market_spent.checked_add(amount).unwrap();
+    // Transfer the rest to the pool vault
+    transfer_lamports(
+        accounts.payer,
+        accounts.pool_vault,
+        accounts.system_program,
+        effects.deposits.pool,
+    )?;
 
-    // Recalculate the game balance after state updates
-    let treasury_lamports = **game_state.to_account_info().lamports.borrow();
-    let game_balance = treasury_lamports
-        .checked_sub(game_state.sell_and_ref_balance).unwrap()
-        .checked_sub(game_state.dev_balance).unwrap()
-        .checked_sub(game_state.premarket_balance).unwrap();
+    // Game balance after the buy
+    let game_balance = game_state.pool_balance;
 
     // Emit an event for Buy
     emit!(Buy {
         game_index:  game_state.game_index,
         event_index: game_state.event_index,
-        player: ctx.accounts.player.key(),
+        player: accounts.player.key(),
         referrer:    player_state.current_referrer,
         game_balance,
         sol_amount: amount,
         shrimp: shrimp_to_add,
         extra_eggs: player_state.extra_eggs,
+        min_shrimp_out,
         timestamp: player_state.last_interaction,
     });
 
     // Update indexes
-    game_state.event_index = game_state.event_index.checked_add(1).unwrap();
-    game_state.game_index  = game_state.game_index.checked_add(1).unwrap();
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    game_state.game_index  = game_state.game_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    // Emit an event for the referral fee and cashback
+    let referral = effects.referral;
+    if referral.total()? > 0 {
+        emit!(ReferralCredited {
+            event_index: game_state.event_index,
+            player: accounts.player.key(),
+            referrer: player_state.current_referrer,
+            sol_amount: amount,
+            referral_fee: referral.referral_fee,
+            cashback: referral.cashback,
+            tier_fees: referral.tier_fees,
+            timestamp: player_state.last_interaction,
+        });
+        game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    }
+
+    // Emit an event once the referrer's attribution window has closed
+    if referral.expired {
+        emit!(ReferralExpired {
+            event_index: game_state.event_index,
+            player: accounts.player.key(),
+            referrer: player_state.current_referrer,
+            referred_at: player_state.referred_at,
+            timestamp: player_state.last_interaction,
+        });
+        game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    }
 
     Ok(())
 }
@@ -133,7 +134,7 @@ pub struct Buy {
     pub player: Pubkey,
     /// Will be `Pubkey::default()` (all 1s) if none is set or if the buyer referred themselves.
     pub referrer: Pubkey,
-    /// The current game balance (after subtracting reserved amounts).
+    /// The pool balance after this event.
     pub game_balance: u64,
     /// The SOL amount involved in the event (spent in a buy).
     pub sol_amount: u64,
@@ -141,6 +142,42 @@ pub struct Buy {
     pub shrimp: u128,
     /// Only for buy, extra eggs
     pub extra_eggs: u128,
+    /// Minimum shrimp the buyer accepted (0 if not set).
+    pub min_shrimp_out: u128,
+    /// The timestamp of the event.
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct ReferralCredited {
+    /// The unique sequential index of this event.
+    pub event_index: u64,
+    /// The buyer's public key.
+    pub player: Pubkey,
+    /// The referrer credited with the referral fee.
+    pub referrer: Pubkey,
+    /// The SOL amount spent in the buy.
+    pub sol_amount: u64,
+    /// Referral fee credited to the referrer.
+    pub referral_fee: u64,
+    /// Cashback credited to the buyer.
+    pub cashback: u64,
+    /// Fees credited to the referrer's referrer (second tier) and theirs (third tier).
+    pub tier_fees: [u64; 2],
+    /// The timestamp of the event.
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct ReferralExpired {
+    /// The unique sequential index of this event.
+    pub event_index: u64,
+    /// The referred player's public key.
+    pub player: Pubkey,
+    /// The referrer who stops earning from the player's buys.
+    pub referrer: Pubkey,
+    /// The timestamp of the player's first referred buy.
+    pub referred_at: u64,
     /// The timestamp of the event.
     pub timestamp: u64,
 }
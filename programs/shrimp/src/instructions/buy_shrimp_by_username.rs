@@ -0,0 +1,14 @@
+use crate::account::BuyByUsernameAccounts;
+use crate::instructions::apply_buy_shrimp;
+use anchor_lang::prelude::*;
+
+pub fn buy_shrimp_by_username(
+    ctx: Context<BuyByUsernameAccounts>,
+    _username: String,
+    amount: u64, 
+    min_shrimp_out: Option<u128>,
+    valid_until: Option<u64>,
+) -> Result<()> {
+    // The referrer was resolved from their username by the account constraints
+    apply_buy_shrimp(ctx.accounts.parts()?, amount, min_shrimp_out, valid_until)
+}
@@ -0,0 +1,74 @@
+use crate::engine;
+use crate::{ state::*, error::* };
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CarryOverPlayer<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.previous_authority)]
+    pub previous_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [player.key().as_ref(), PlayerState::SEED, previous_authority.key().as_ref()],
+        bump
+    )]
+    pub previous_player_state: Box<Account<'info, PlayerState>>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + PlayerState::INIT_SPACE,
+        seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub player_state: Box<Account<'info, PlayerState>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn carry_over_player(ctx: Context<CarryOverPlayer>) -> Result<()> {
+    // Carry over the player bonuses
+    engine::carry_over_player(&ctx.accounts.game_state, &ctx.accounts.previous_player_state, &mut ctx.accounts.player_state)?;
+
+    // Emit event
+    let game_state = &mut ctx.accounts.game_state;
+    let now: u64 = current_timestamp()?;
+    emit!(PlayerCarriedOver {
+        event_index: game_state.event_index,
+        player: ctx.accounts.player.key(),
+        testnet_player: ctx.accounts.player_state.testnet_player,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct PlayerCarriedOver {
+    /// The unique sequential index of this event.
+    pub event_index: u64,
+    /// The address of the player carried over.
+    pub player: Pubkey,
+    /// Whether the player keeps the testnet bonus.
+    pub testnet_player: bool,
+    /// The timestamp when the event was emitted.
+    pub timestamp: u64,
+}
@@ -0,0 +1,129 @@
+use crate::{ state::*, error::* };
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct ChangeUsername<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PlayerProfile::SEED, player.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    #[account(
+        mut,
+        close = player,
+        seeds = [UsernameToAddress::SEED, profile.username.as_bytes()],
+        bump
+    )]
+    pub old_username_to_address_account: Account<'info, UsernameToAddress>,
+
+    #[account(seeds = [UsernamePolicy::SEED], bump)]
+    pub username_policy: Account<'info, UsernamePolicy>,
+
+    /// CHECK: Must be empty, checked with `check_not_reserved`
+    #[account(seeds = [ReservedName::SEED, username.as_bytes()], bump)]
+    pub reserved_name: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + UsernameToAddress::INIT_SPACE,
+        seeds = [UsernameToAddress::SEED, username.as_bytes()],
+        bump
+    )]
+    pub username_to_address_account: Account<'info, UsernameToAddress>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn change_username(ctx: Context<ChangeUsername>, username: String) -> Result<()> {
+    // Check username format and that it is not reserved
+    check_username(&username, &ctx.accounts.username_policy.rules)?;
+    check_not_reserved(&ctx.accounts.reserved_name)?;
+
+    // The fee goes to the pool, so the game must still be running
+    let game_state = &mut ctx.accounts.game_state;
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+
+    // Check the cooldown since the last change
+    let profile = &mut ctx.accounts.profile;
+    let now: u64 = current_timestamp()?;
+    check_username_cooldown(profile, now)?;
+
+    // Check the new username is different and free
+    require!(profile.username != username, CustomErrors::InvalidUsername);
+    let username_to_address = &mut ctx.accounts.username_to_address_account;
+    require!(
+        username_to_address.address == Pubkey::default(),
+        CustomErrors::UsernameTaken
+    );
+
+    // Pay the fee into the pool
+    let fee = game_state.username_fee;
+    transfer_lamports(
+        &ctx.accounts.player,
+        &ctx.accounts.pool_vault,
+        &ctx.accounts.system_program,
+        fee,
+    )?;
+    game_state.pool_balance = game_state.pool_balance.checked_add(fee).ok_or(CustomErrors::MathOverflow)?;
+
+    // Reassign the username (the old mapping is closed and its rent refunded)
+    let old_username = std::mem::replace(&mut profile.username, username.clone());
+    profile.name_changed_at = now;
+    username_to_address.address = ctx.accounts.player.key();
+
+    // Emit event
+    emit!(UsernameChanged {
+        event_index: game_state.event_index,
+        player: ctx.accounts.player.key(),
+        old_username,
+        username,
+        fee,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct UsernameChanged {
+    /// The unique sequential index of this event.
+    pub event_index: u64,
+    /// The address of the player.
+    pub player: Pubkey,
+    /// The username given up, now free to claim.
+    pub old_username: String,
+    /// The username now held by the player.
+    pub username: String,
+    /// The lamports paid into the pool.
+    pub fee: u64,
+    /// The timestamp when the event was emitted.
+    pub timestamp: u64,
+}
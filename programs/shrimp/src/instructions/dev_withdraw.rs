@@ -18,6 +18,9 @@ pub struct DevWithdraw<'info> {
     )]
     pub game_state: Box<Account<'info, GameState>>,
 
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
     /// CHECK: Custom contraint
     #[account(mut, address = game_state.dev1)]
     dev1: UncheckedAccount<'info>,
@@ -37,19 +40,16 @@ pub fn dev_withdraw(ctx: Context<DevWithdraw>) -> Result<()> {
     // Check one of the devs or authority is a signer
     require!(ctx.accounts.authority.is_signer || ctx.accounts.dev1.is_signer || ctx.accounts.dev2.is_signer || ctx.accounts.dev3.is_signer, self::CustomErrors::InvalidSigner);
 
-    // Retrieve game state data and treasury PDA
+    // Retrieve game state data and the dev vault signer
     let game_state = &mut ctx.accounts.game_state;
-    let treasury_account = &game_state.to_account_info();
-
-    // Calculate rent exemption minimum balance for treasury
-    let rent = solana_program::rent::Rent::get().unwrap();
-    let rent_exemption = rent.minimum_balance(8 + GameState::INIT_SPACE);
+    let authority = ctx.accounts.authority.key();
+    let dev_bump = ctx.bumps.dev_vault;
 
     // Check there are any funds available to withdraw
-    require!(game_state.dev_balance > rent_exemption, self::CustomErrors::InsufficientFunds);
+    require!(game_state.dev_balance > 0, self::CustomErrors::InsufficientFunds);
 
-    // Calculate amount of dev balance left after rent exemption
-    let dev_balance = game_state.dev_balance - rent_exemption;
+    // The whole dev balance is paid out (the vault keeps its rent)
+    let dev_balance = game_state.dev_balance;
 
     // Calculate the base amount (5% of dev_balance)
     let base_amount = dev_balance / 20;
@@ -62,35 +62,47 @@ pub fn dev_withdraw(ctx: Context<DevWithdraw>) -> Result<()> {
     let dev1_amount = dev_balance - (dev2_amount + dev3_amount);
 
     // Reset dev balance
-    game_state.dev_balance = rent_exemption;
+    game_state.dev_balance = 0;
 
-    // Send 45% from treasury to dev1
-    transfer_lamports_from_owned_pda(
-        treasury_account,
+    // Send 45% from the dev vault to dev1
+    transfer_lamports_from_vault(
+        &ctx.accounts.dev_vault,
         &ctx.accounts.dev1,
-        dev1_amount as u64,
+        &ctx.accounts.system_program,
+        DEV_VAULT_SEED,
+        &authority,
+        dev_bump,
+        dev1_amount,
     )?;
 
-    // Send 40% from treasury to the dev2
-    transfer_lamports_from_owned_pda(
-        treasury_account,
+    // Send 40% from the dev vault to the dev2
+    transfer_lamports_from_vault(
+        &ctx.accounts.dev_vault,
         &ctx.accounts.dev2,
-        dev2_amount as u64,
+        &ctx.accounts.system_program,
+        DEV_VAULT_SEED,
+        &authority,
+        dev_bump,
+        dev2_amount,
     )?;
 
-    // Send 15% from treasury to the dev3
-    transfer_lamports_from_owned_pda(
-        treasury_account,
+    // Send 15% from the dev vault to the dev3
+    transfer_lamports_from_vault(
+        &ctx.accounts.dev_vault,
         &ctx.accounts.dev3,
-        dev3_amount as u64,
+        &ctx.accounts.system_program,
+        DEV_VAULT_SEED,
+        &authority,
+        dev_bump,
+        dev3_amount,
     )?;
 
     // Emit event
     emit!(DevWithdrawn {
-        dev_balance: dev_balance as u64,
-        dev1_amount: dev1_amount as u64,
-        dev2_amount: dev2_amount as u64,
-        dev3_amount: dev3_amount as u64,
+        dev_balance,
+        dev1_amount,
+        dev2_amount,
+        dev3_amount,
         authority: ctx.accounts.authority.key()
     });
 
@@ -98,10 +110,10 @@ pub fn dev_withdraw(ctx: Context<DevWithdraw>) -> Result<()> {
 }
 
 #[event]
-struct DevWithdrawn {
-    dev_balance: u64,
-    dev1_amount: u64,
-    dev2_amount: u64,
-    dev3_amount:u64,
-    authority: Pubkey,
+pub struct DevWithdrawn {
+    pub dev_balance: u64,
+    pub dev1_amount: u64,
+    pub dev2_amount: u64,
+    pub dev3_amount:u64,
+    pub authority: Pubkey,
 }
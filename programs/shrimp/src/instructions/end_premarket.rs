@@ -1,7 +1,7 @@
 use crate::{error::*, state::*};
+use crate::helpers::current_timestamp;
 use anchor_lang::prelude::*;
 use solana_program::pubkey::Pubkey;
-use anchor_lang::solana_program::clock;
 
 #[derive(Accounts)]
 pub struct EndPremarket<'info> {
@@ -26,7 +26,7 @@ pub fn end_premarket(
     require!(game_state.test_env, CustomErrors::NotTestEnv);
 
     // Check game is actually in premarket
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+    let now: u64 = current_timestamp()?;
     require!(
         now < game_state.premarket_end,
         CustomErrors::PreMarketOver
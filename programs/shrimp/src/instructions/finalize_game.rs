@@ -0,0 +1,81 @@
+use crate::engine;
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct FinalizeGame<'info> {
+    /// Anyone can finalize a game that has reached its end condition
+    pub caller: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn finalize_game(ctx: Context<FinalizeGame>) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+    let now: u64 = current_timestamp()?;
+
+    // End the game once the market is at the endgame limit
+    engine::finalize_game(game_state, now)?;
+
+    // Emit event
+    emit_game_over(game_state, ctx.accounts.caller.key(), now)
+}
+
+// Emit `GameOver` with the final market and balances, and bump the indexes
+pub fn emit_game_over(game_state: &mut GameState, ended_by: Pubkey, now: u64) -> Result<()> {
+    emit!(GameOver {
+        game_index: game_state.game_index,
+        event_index: game_state.event_index,
+        ended_by,
+        market_eggs: game_state.market_eggs,
+        final_balance: game_state.final_balance,
+        prize_mode: game_state.prize_mode,
+        prize_weight: game_state.prize_weight,
+        premarket_balance: game_state.premarket_balance,
+        dev_balance: game_state.dev_balance,
+        sell_and_ref_balance: game_state.sell_and_ref_balance,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+    game_state.game_index  = game_state.game_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct GameOver {
+    // The unique sequential index of this game event.
+    pub game_index: u64,
+    /// The unique sequential index of this event.
+    pub event_index: u64,
+    /// The seller whose sell ended the game, or the caller of `finalize_game`.
+    pub ended_by: Pubkey,
+    /// The final market eggs value.
+    pub market_eggs: u128,
+    /// The pool balance paid out as the prize.
+    pub final_balance: u64,
+    /// How the prize is shared out.
+    pub prize_mode: PrizeMode,
+    /// The total weight the prize is shared by (premarket spend, shrimp or market spend).
+    pub prize_weight: u128,
+    /// Premarket earnings still to be withdrawn.
+    pub premarket_balance: u64,
+    /// Dev fees still to be withdrawn.
+    pub dev_balance: u64,
+    /// Sell and referral earnings still to be withdrawn.
+    pub sell_and_ref_balance: u64,
+    /// The timestamp when the game ended.
+    pub timestamp: u64,
+}
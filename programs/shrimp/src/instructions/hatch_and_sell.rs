@@ -0,0 +1,41 @@
+use crate::account::SellAndHatchAccounts;
+use crate::engine;
+use crate::helpers::*;
+use crate::instructions::{emit_hatch, settle_sell};
+use anchor_lang::prelude::*;
+
+pub fn hatch_and_sell(
+    ctx: Context<SellAndHatchAccounts>,
+    sell_bps: u16,
+    min_lamports_out: Option<u64>,
+    valid_until: Option<u64>,
+) -> Result<()> {
+    // Check transaction restrictions
+    limit_instructions(&ctx.accounts.sysvar_instructions, ctx.accounts.game_state.max_ixs, &ctx.accounts.game_state.program_whitelist)?;
+
+    // Get state
+    let player_state = &mut ctx.accounts.player_state;
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Get the current time
+    let now: u64 = current_timestamp()?;
+
+    // Reject the order if it landed after its deadline
+    check_deadline(valid_until, now)?;
+
+    // Bonus from the NFT (if supplied) and testnet participation
+    let bonus_percent = get_bonus_percent(game_state, player_state, &ctx.accounts.nft_asset, ctx.accounts.player.key())?;
+
+    // Sell the share of the eggs and hatch the rest
+    let min_lamports_out = min_lamports_out.unwrap_or_default();
+    let effects = engine::hatch_and_sell(game_state, player_state, sell_bps, bonus_percent, min_lamports_out, now)?;
+
+    settle_sell(ctx.accounts, ctx.bumps.pool_vault, &effects.sell, min_lamports_out, now)?;
+
+    // Nothing is hatched once the sell has ended the game
+    if let Some(hatch) = effects.hatch {
+        emit_hatch(&mut ctx.accounts.game_state, ctx.accounts.player.key(), &hatch, now)?;
+    }
+
+    Ok(())
+}
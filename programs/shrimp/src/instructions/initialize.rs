@@ -1,9 +1,9 @@
 use crate::{error::*, state::*};
+use crate::helpers::{check_curve_params, check_fee_schedule, fund_vaults};
 use anchor_lang::prelude::*;
 use anchor_lang::{
     prelude::{Account, Program, Result},
     system_program::System,
-    ToAccountInfo,
 };
 
 #[derive(Accounts)]
@@ -23,6 +23,24 @@ pub struct Initialize<'info> {
     )]
     pub lock_state: Account<'info, LockState>,
 
+    #[account(
+        init_if_needed,
+        seeds = [GameRegistry::SEED], 
+        bump, 
+        payer = authority, 
+        space = 8 + GameRegistry::INIT_SPACE
+    )]
+    pub game_registry: Account<'info, GameRegistry>,
+
+    #[account(
+        init_if_needed,
+        seeds = [UsernamePolicy::SEED], 
+        bump, 
+        payer = authority, 
+        space = 8 + UsernamePolicy::INIT_SPACE
+    )]
+    pub username_policy: Account<'info, UsernamePolicy>,
+
     #[account(
         init, 
         seeds = [GameState::SEED, authority.key().as_ref()], 
@@ -32,9 +50,22 @@ pub struct Initialize<'info> {
     )]
     pub game_state: Account<'info, GameState>,
 
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
     system_program: Program<'info, System>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn initialize(
     ctx: Context<Initialize>,
     dev1: Pubkey,
@@ -43,55 +74,129 @@ pub fn initialize(
     premarket_end: u64,
     cooldown: u64,
     test_env: bool,
+    fees: Option<FeeSchedule>,
+    curve: Option<CurveParams>,
+    prize_mode: Option<PrizeMode>,
 ) -> Result<()> {
-    // Check dev keys
-    require!(dev1 != dev2 && dev1 != dev3 && dev2 != dev3, CustomErrors::InvalidDevs);
-
     // Check owner
     require!(ctx.accounts.owner.key() == pubkey!("CdKqXMm7QDjMwfFR3GgWTRQE7x39BFbiLm8KWC4TibzR"), CustomErrors::InvalidOwner);
 
     // Initialize game state
+    let authority = ctx.accounts.authority.key();
     let game_state = &mut ctx.accounts.game_state;
-    game_state.authority = ctx.accounts.authority.key();
-    game_state.market_eggs = MARKET_START.into();
-    game_state.dev1 = dev1;
-    game_state.dev2 = dev2;
-    game_state.dev3 = dev3;
-    game_state.dev_balance = **game_state.to_account_info().lamports.borrow();
-    game_state.event_index = 1;
-    game_state.game_index = 1;
-    game_state.premarket_end = premarket_end;
-    game_state.cooldown = cooldown;
-    game_state.test_env = test_env;
-    game_state.max_ixs = 5;
-    game_state.program_whitelist = vec!();
+    setup_game(game_state, authority, [dev1, dev2, dev3], premarket_end, cooldown, test_env, fees, curve, prize_mode)?;
+    game_state.season = 1;
+
+    // The first game is season 1 of the registry. Later games (test environments
+    // only, the lock stops them on mainnet) leave it alone: seasons move on with `start_season`
+    let registry = &mut ctx.accounts.game_registry;
+    if registry.season == 0 {
+        registry.owner = ctx.accounts.owner.key();
+        registry.season = 1;
+        registry.authority = authority;
+    }
 
-    // For mainnet deploy, prevent multiple initializes
+    // Usernames follow the default rules until the owner sets a policy
+    let username_policy = &mut ctx.accounts.username_policy;
+    if username_policy.rules.max_len == 0 {
+        username_policy.rules = UsernameRules::default();
+    }
+
+    // Fund each vault up to its rent
+    fund_vaults(
+        &ctx.accounts.authority,
+        [
+            &ctx.accounts.dev_vault,
+            &ctx.accounts.premarket_vault,
+            &ctx.accounts.payables_vault,
+            &ctx.accounts.pool_vault,
+        ],
+        &ctx.accounts.system_program,
+    )?;
+
+    // For mainnet deploy, prevent multiple initializes (later seasons use `start_season`)
     require!(!ctx.accounts.lock_state.locked, CustomErrors::InitLocked);
     if !test_env {
         ctx.accounts.lock_state.locked = true;
     }
 
     // Emit event
+    let game_state = &ctx.accounts.game_state;
     emit!(Initialized {
-        dev1: dev1,
-        dev2: dev2,
-        dev3: dev3,
-        owner: ctx.accounts.authority.key(),
-        premarket_end: premarket_end,
-        test_env: test_env
+        dev1,
+        dev2,
+        dev3,
+        owner: authority,
+        premarket_end,
+        test_env,
+        fees: game_state.fees,
+        curve: game_state.curve,
+        prize_mode: game_state.prize_mode,
     }); 
     
     // Success
     Ok(())
 }
 
-#[event]
-struct Initialized {
-    dev1: Pubkey,
-    dev2: Pubkey,
-    dev3: Pubkey,
-    owner: Pubkey,
+/// Validate the game settings and write them to a new `GameState`, the way
+/// `initialize` and `start_season` set up a game
+#[allow(clippy::too_many_arguments)]
+pub fn setup_game(
+    game_state: &mut GameState,
+    authority: Pubkey,
+    devs: [Pubkey; 3],
     premarket_end: u64,
-    test_env: bool
+    cooldown: u64,
+    test_env: bool,
+    fees: Option<FeeSchedule>,
+    curve: Option<CurveParams>,
+    prize_mode: Option<PrizeMode>,
+) -> Result<()> {
+    // Check dev keys
+    let [dev1, dev2, dev3] = devs;
+    require!(dev1 != dev2 && dev1 != dev3 && dev2 != dev3, CustomErrors::InvalidDevs);
+
+    // Check fee schedule, defaulting to the standard fees
+    let fees = fees.unwrap_or_default();
+    check_fee_schedule(&fees)?;
+
+    // Check curve, defaulting to the standard curve
+    let curve = curve.unwrap_or_default();
+    check_curve_params(&curve)?;
+
+    // Prize goes to premarket buyers unless another mode is chosen
+    let prize_mode = prize_mode.unwrap_or_default();
+
+    game_state.authority = authority;
+    game_state.market_eggs = curve.market_start;
+    game_state.fees = fees;
+    game_state.curve = curve;
+    game_state.prize_mode = prize_mode;
+    game_state.claim_window = CLAIM_WINDOW;
+    game_state.referral_window = REFERRAL_WINDOW;
+    game_state.dev1 = dev1;
+    game_state.dev2 = dev2;
+    game_state.dev3 = dev3;
+    game_state.event_index = 1;
+    game_state.game_index = 1;
+    game_state.premarket_end = premarket_end;
+    game_state.cooldown = cooldown;
+    game_state.test_env = test_env;
+    game_state.max_ixs = 5;
+    game_state.program_whitelist = vec!();
+
+    Ok(())
+}
+
+#[event]
+pub struct Initialized {
+    pub dev1: Pubkey,
+    pub dev2: Pubkey,
+    pub dev3: Pubkey,
+    pub owner: Pubkey,
+    pub premarket_end: u64,
+    pub test_env: bool,
+    pub fees: FeeSchedule,
+    pub curve: CurveParams,
+    pub prize_mode: PrizeMode,
 }
@@ -1,11 +1,11 @@
 use crate::{error::*};
+use crate::helpers::current_timestamp;
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::{
     instruction::{AccountMeta, Instruction},
     program::invoke_signed,
     sysvar
 };
-use anchor_lang::solana_program::clock;
 use crate::state::{GameState, PlayerState, CANDY_MACHINE_AUTHORITY_SEED, NFT_MIN_BUY};
 
 /// The mint asset discriminator for candy machine
@@ -164,7 +164,7 @@ pub fn mint_nft(ctx: Context<MintNft>) -> Result<()> {
     game_state.nfts_minted += 1;
 
     // Emit an event with the updated total supply (nfts_minted)
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+    let now: u64 = current_timestamp()?;
     emit!(NftMinted {
         event_index: game_state.event_index,
         player: ctx.accounts.player.key(),
@@ -173,7 +173,7 @@ pub fn mint_nft(ctx: Context<MintNft>) -> Result<()> {
     });
 
     // Update indexes
-    game_state.event_index = game_state.event_index.checked_add(1).unwrap();
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
 
     Ok(())
 }
@@ -1,6 +1,14 @@
 pub use self::{initialize::*, sell_eggs::*, buy_premarket::*, hatch_eggs::*, buy_shrimp::*, set_collection::*, 
     dev_withdraw::*, user_withdraw::*, set_market::*, register::*, mint_nft::*, set_program_guards::*, 
-    end_premarket::*, testnet_bonus::*, set_minter::*, admin_mint::*};
+    end_premarket::*, testnet_bonus::*, set_minter::*, admin_mint::*,
+    quote_buy::*, quote_sell::*, quote_hatch::*, set_fee_schedule::*, finalize_game::*,
+    set_claim_policy::*, sweep_unclaimed::*, start_season::*, carry_over_player::*,
+    change_username::*, release_username::*, set_username_fee::*, set_username_policy::*,
+    reserve_username::*, unreserve_username::*,
+    buy_shrimp_by_username::*, buy_premarket_by_username::*, set_referrer::*, set_referrer_policy::*,
+    set_referral_split::*, set_referral_tiers::*,
+    set_referral_guards::*, set_referral_window::*,
+    sell_eggs_partial::*, hatch_and_sell::*};
 
 pub mod initialize;
 pub mod dev_withdraw;
@@ -17,4 +25,29 @@ pub mod set_program_guards;
 pub mod end_premarket;
 pub mod testnet_bonus;
 pub mod set_minter;
-pub mod admin_mint;
\ No newline at end of file
+pub mod admin_mint;
+pub mod quote_buy;
+pub mod quote_sell;
+pub mod quote_hatch;
+pub mod set_fee_schedule;
+pub mod finalize_game;
+pub mod set_claim_policy;
+pub mod sweep_unclaimed;
+pub mod start_season;
+pub mod carry_over_player;
+pub mod change_username;
+pub mod release_username;
+pub mod set_username_fee;
+pub mod set_username_policy;
+pub mod reserve_username;
+pub mod unreserve_username;
+pub mod buy_shrimp_by_username;
+pub mod buy_premarket_by_username;
+pub mod set_referrer;
+pub mod set_referrer_policy;
+pub mod set_referral_split;
+pub mod set_referral_tiers;
+pub mod set_referral_guards;
+pub mod set_referral_window;
+pub mod sell_eggs_partial;
+pub mod hatch_and_sell;
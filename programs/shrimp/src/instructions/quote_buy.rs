@@ -0,0 +1,34 @@
+use crate::engine;
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct QuoteBuy<'info> {
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+}
+
+/// Read-only: prices a `buy_shrimp` of `amount` lamports with the same math and checks
+pub fn quote_buy(ctx: Context<QuoteBuy>, amount: u64) -> Result<BuyQuote> {
+    let game_state = &ctx.accounts.game_state;
+
+    // Check amount
+    require!(
+        amount >= MIN_BUY,
+        CustomErrors::BuyAmountTooLow
+    );
+
+    // Check game is out of premarket and not over
+    let now: u64 = current_timestamp()?;
+    engine::require_market_open(game_state, now)?;
+
+    engine::quote_buy(game_state, amount)
+}
@@ -0,0 +1,21 @@
+use crate::account::QuoteEggsAccounts;
+use crate::engine;
+use crate::state::*;
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+/// Read-only: prices a `sell_eggs` for the player with the same math and checks.
+/// The sell cooldown is not enforced so the quote can be requested at any time.
+pub fn quote_sell(ctx: Context<QuoteEggsAccounts>) -> Result<SellQuote> {
+    let game_state = &ctx.accounts.game_state;
+    let player_state = &ctx.accounts.player_state;
+
+    // Ensure the game is out of premarket and not over
+    let now: u64 = current_timestamp()?;
+    engine::require_market_open(game_state, now)?;
+
+    // Bonus from the NFT (if supplied) and testnet participation
+    let bonus_percent = get_bonus_percent(game_state, player_state, &ctx.accounts.nft_asset, ctx.accounts.player.key())?;
+
+    engine::quote_sell(game_state, player_state, bonus_percent, now)
+}
@@ -1,7 +1,6 @@
 use crate::{ state::*, error::* };
 use crate::helpers::*;
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::clock;
 use solana_program::sysvar;
 
 #[derive(Accounts)]
@@ -33,20 +32,27 @@ pub struct Register<'info> {
     #[account(
         init_if_needed,
         payer = player,
-        space = 8 + UsernameToAddress::INIT_SPACE,
-        seeds = [UsernameToAddress::SEED, username.as_bytes(), authority.key().as_ref()],
+        space = 8 + PlayerProfile::INIT_SPACE,
+        seeds = [PlayerProfile::SEED, player.key().as_ref()],
         bump
     )]
-    pub username_to_address_account: Account<'info, UsernameToAddress>,
+    pub profile: Account<'info, PlayerProfile>,
+
+    #[account(seeds = [UsernamePolicy::SEED], bump)]
+    pub username_policy: Account<'info, UsernamePolicy>,
+
+    /// CHECK: Must be empty, checked with `check_not_reserved`
+    #[account(seeds = [ReservedName::SEED, username.as_bytes()], bump)]
+    pub reserved_name: UncheckedAccount<'info>,
 
     #[account(
         init_if_needed,
         payer = player,
-        space = 8 + AddressToUsername::INIT_SPACE,
-        seeds = [AddressToUsername::SEED, player.key().as_ref(), authority.key().as_ref()],
+        space = 8 + UsernameToAddress::INIT_SPACE,
+        seeds = [UsernameToAddress::SEED, username.as_bytes()],
         bump
     )]
-    pub address_to_username_account: Account<'info, AddressToUsername>,
+    pub username_to_address_account: Account<'info, UsernameToAddress>,
 
     /// CHECK: account constraints checked in account trait
     #[account(address = sysvar::instructions::id())]
@@ -57,15 +63,7 @@ pub struct Register<'info> {
 
 pub fn register(ctx: Context<Register>, username: String) -> Result<()> {
     // Check transaction restrictions
-    limit_instructions(&ctx.accounts.sysvar_instructions, ctx.accounts.game_state.max_ixs, &ctx.accounts.game_state.program_whitelist).unwrap();
-
-    // Username must be 1-12 characters, all lowercase ASCII letters
-    if username.len() < 1
-        || username.len() > AddressToUsername::MAX_USERNAME_LENGTH
-        || !username.chars().all(|c| c.is_ascii_lowercase())
-    {
-        return err!(CustomErrors::InvalidUsername);
-    }
+    limit_instructions(&ctx.accounts.sysvar_instructions, ctx.accounts.game_state.max_ixs, &ctx.accounts.game_state.program_whitelist)?;
 
     // Check player has at least a minimum buy
     require!(ctx.accounts.player_state.market_spent + ctx.accounts.player_state.premarket_spent >= MIN_BUY, CustomErrors::MinBuyNotMet);
@@ -74,36 +72,56 @@ pub fn register(ctx: Context<Register>, username: String) -> Result<()> {
     let game_state = &mut ctx.accounts.game_state;
 
     let username_to_address = &mut ctx.accounts.username_to_address_account;
-    let address_to_username = &mut ctx.accounts.address_to_username_account;
-
-    // Check if username is taken
-    if username_to_address.address != Pubkey::default()
-        && username_to_address.address != *ctx.accounts.player.key
-    {
-        return err!(CustomErrors::UsernameTaken);
+    let profile = &mut ctx.accounts.profile;
+    let now: u64 = current_timestamp()?;
+
+    let claiming = profile.username.is_empty();
+    if claiming {
+        // Check username format and that it is not reserved
+        check_username(&username, &ctx.accounts.username_policy.rules)?;
+        check_not_reserved(&ctx.accounts.reserved_name)?;
+
+        // A released username can only be replaced after the cooldown
+        check_username_cooldown(profile, now)?;
+
+        // Check if username is taken
+        if username_to_address.address != Pubkey::default()
+            && username_to_address.address != *ctx.accounts.player.key
+        {
+            return err!(CustomErrors::UsernameTaken);
+        }
+
+        // Claim the username for every game
+        profile.username = username.clone();
+        if profile.registered_at == 0 {
+            profile.registered_at = now;
+        }
+        username_to_address.address = *ctx.accounts.player.key;
+    } else {
+        // Players keep the username they claimed first
+        require!(profile.username == username, CustomErrors::AlreadyRegistered);
     }
 
-    // Check if player address has already registered
-    if !address_to_username.username.is_empty() {
-        return err!(CustomErrors::AlreadyRegistered);
+    // Register in this game, unless the player only claims a new username after a release
+    let player_state = &mut ctx.accounts.player_state;
+    if player_state.registered {
+        require!(claiming, CustomErrors::AlreadyRegistered);
+    } else {
+        player_state.registered = true;
+        profile.seasons_played = profile.seasons_played.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
     }
 
-    // Update state
-    address_to_username.username = username.clone();
-    username_to_address.address = *ctx.accounts.player.key;
-    ctx.accounts.player_state.registered = true;
-
     // Emit event
-    let now: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
     emit!(UserRegistered {
         event_index: game_state.event_index,
         player: ctx.accounts.player.key(),
         username,
+        seasons_played: profile.seasons_played,
         timestamp: now,
     });
 
     // Update indexes
-    game_state.event_index = game_state.event_index.checked_add(1).unwrap();
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
 
     Ok(())
 }
@@ -116,6 +134,8 @@ pub struct UserRegistered {
     pub player: Pubkey,
     /// The selected username of player.
     pub username: String,
+    /// The number of games the player has registered in, this one included.
+    pub seasons_played: u64,
     /// The timestamp when the event was emitted.
     pub timestamp: u64,
 }
@@ -0,0 +1,51 @@
+use crate::state::*;
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ReleaseUsername<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PlayerProfile::SEED, player.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    #[account(
+        mut,
+        close = player,
+        seeds = [UsernameToAddress::SEED, profile.username.as_bytes()],
+        bump
+    )]
+    pub username_to_address_account: Account<'info, UsernameToAddress>,
+}
+
+pub fn release_username(ctx: Context<ReleaseUsername>) -> Result<()> {
+    // Free the username (the mapping is closed and its rent refunded)
+    let profile = &mut ctx.accounts.profile;
+    let now: u64 = current_timestamp()?;
+    let username = std::mem::take(&mut profile.username);
+    profile.name_changed_at = now;
+
+    // Emit event
+    emit!(UsernameReleased {
+        player: ctx.accounts.player.key(),
+        username,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event]
+pub struct UsernameReleased {
+    /// The address of the player.
+    pub player: Pubkey,
+    /// The username given up, now free to claim.
+    pub username: String,
+    /// The timestamp when the event was emitted.
+    pub timestamp: u64,
+}
@@ -0,0 +1,46 @@
+use crate::state::*;
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct ReserveUsername<'info> {
+    #[account(mut, address = game_registry.owner)]
+    owner: Signer<'info>,
+
+    #[account(seeds = [GameRegistry::SEED], bump)]
+    pub game_registry: Account<'info, GameRegistry>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ReservedName::INIT_SPACE,
+        seeds = [ReservedName::SEED, username.as_bytes()],
+        bump
+    )]
+    pub reserved_name: Account<'info, ReservedName>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn reserve_username(ctx: Context<ReserveUsername>, username: String) -> Result<()> {
+    // Update state (a player already holding the username keeps it)
+    let now: u64 = current_timestamp()?;
+    ctx.accounts.reserved_name.reserved_at = now;
+
+    // Emit event
+    emit!(ReservedNameSet {
+        username,
+        reserved: true,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event]
+pub struct ReservedNameSet {
+    pub username: String,
+    pub reserved: bool,
+    pub timestamp: u64,
+}
@@ -0,0 +1,55 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetClaimPolicy<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_claim_policy(ctx: Context<SetClaimPolicy>, claim_window: u64, destination: SweepDestination) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // The policy is locked once the game is over
+    require!(
+        !game_state.game_over,
+        CustomErrors::GameOver
+    );
+
+    // Validate inputs
+    check_claim_policy(game_state, claim_window, &destination)?;
+
+    // Update state
+    game_state.claim_window = claim_window;
+    game_state.sweep_destination = destination;
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(ClaimPolicySet {
+        event_index: game_state.event_index,
+        claim_window,
+        destination,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ClaimPolicySet {
+    pub event_index: u64,
+    pub claim_window: u64,
+    pub destination: SweepDestination,
+    pub timestamp: u64,
+}
@@ -49,7 +49,7 @@ pub fn set_collection(
     // Deserialize and retrieve collection key from the supplied candy machine
     {
         let mut data_slice: &[u8] = &ctx.accounts.candy_machine.data.borrow();
-        let candy_machine_data: CandyMachine = AccountDeserialize::try_deserialize(&mut data_slice).unwrap();
+        let candy_machine_data: CandyMachine = AccountDeserialize::try_deserialize(&mut data_slice)?;
         game_state.collection_key = candy_machine_data.collection_mint;
         game_state.candymachine_key = *ctx.accounts.candy_machine.key;
     }
@@ -80,7 +80,7 @@ pub fn set_collection(
     // Invoke with PDA signer
     invoke_signed(
         &ix,
-        &account_infos.as_slice(),
+        account_infos.as_slice(),
         &[
             &[
                 CANDY_MACHINE_AUTHORITY_SEED.as_bytes(),
@@ -100,9 +100,9 @@ pub fn set_collection(
 }
 
 #[event]
-struct NFTCollectionSet {
-    collection: Pubkey,
-    authority: Pubkey,
+pub struct NFTCollectionSet {
+    pub collection: Pubkey,
+    pub authority: Pubkey,
 }
 
 /// Candy machine configuration data.
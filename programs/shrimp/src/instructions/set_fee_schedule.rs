@@ -0,0 +1,54 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetFeeSchedule<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_fee_schedule(ctx: Context<SetFeeSchedule>, fees: FeeSchedule) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Fees are locked once the premarket closes
+    let now: u64 = current_timestamp()?;
+    require!(
+        now < game_state.premarket_end,
+        CustomErrors::PreMarketOver
+    );
+
+    // Validate inputs
+    check_fee_schedule(&fees)?;
+    check_referral_tiers(&fees, game_state.referral_tiers)?;
+    check_referral_guards(&fees, &game_state.referral_guards)?;
+
+    // Update state
+    game_state.fees = fees;
+
+    // Emit event
+    emit!(FeeScheduleSet {
+        event_index: game_state.event_index,
+        fees,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct FeeScheduleSet {
+    pub event_index: u64,
+    pub fees: FeeSchedule,
+    pub timestamp: u64,
+}
@@ -31,6 +31,6 @@ pub fn set_market(ctx: Context<SetMarket>, market_eggs: u128) -> Result<()> {
 }
 
 #[event]
-struct MarketUpdated {
-    new_market_eggs: u128,
+pub struct MarketUpdated {
+    pub new_market_eggs: u128,
 }
\ No newline at end of file
@@ -30,7 +30,7 @@ pub fn set_minter(ctx: Context<SetMinter>, minter: Pubkey) -> Result<()> {
     
     emit!(MinterSet {
         authority: ctx.accounts.authority.key(),
-        minter: minter,
+        minter,
         timestamp: Clock::get()?.unix_timestamp as u64,
     });
     
@@ -0,0 +1,52 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferralGuards<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_referral_guards(ctx: Context<SetReferralGuards>, guards: ReferralGuards) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Guards are locked once the premarket closes, like the fees they protect
+    let now: u64 = current_timestamp()?;
+    require!(
+        now < game_state.premarket_end,
+        CustomErrors::PreMarketOver
+    );
+
+    // Validate inputs
+    check_referral_guards(&game_state.fees, &guards)?;
+
+    // Update state
+    game_state.referral_guards = guards;
+
+    // Emit event
+    emit!(ReferralGuardsSet {
+        event_index: game_state.event_index,
+        guards,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferralGuardsSet {
+    pub event_index: u64,
+    pub guards: ReferralGuards,
+    pub timestamp: u64,
+}
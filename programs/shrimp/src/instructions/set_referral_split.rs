@@ -0,0 +1,62 @@
+use crate::{ state::*, error::* };
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferralSplit<'info> {
+    pub player: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        mut,
+        seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub player_state: Box<Account<'info, PlayerState>>,
+}
+
+pub fn set_referral_split(ctx: Context<SetReferralSplit>, cashback: u64) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+    let player_state = &mut ctx.accounts.player_state;
+
+    // Validate inputs
+    check_referral_split(&game_state.fees, cashback)?;
+
+    // Update state
+    player_state.referee_cashback = cashback;
+    let (referral_fee, cashback) = referral_split(&game_state.fees, player_state, 0);
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(ReferralSplitSet {
+        event_index: game_state.event_index,
+        referrer: ctx.accounts.player.key(),
+        referral_fee,
+        cashback,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferralSplitSet {
+    pub event_index: u64,
+    pub referrer: Pubkey,
+    pub referral_fee: u64,       // % of referred buys kept by the referrer
+    pub cashback: u64,           // % of referred buys given back to the referee
+    pub timestamp: u64,
+}
@@ -0,0 +1,52 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferralTiers<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_referral_tiers(ctx: Context<SetReferralTiers>, tiers: [u64; 2]) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Tiers are locked once the premarket closes, like the fees they split
+    let now: u64 = current_timestamp()?;
+    require!(
+        now < game_state.premarket_end,
+        CustomErrors::PreMarketOver
+    );
+
+    // Validate inputs
+    check_referral_tiers(&game_state.fees, tiers)?;
+
+    // Update state
+    game_state.referral_tiers = tiers;
+
+    // Emit event
+    emit!(ReferralTiersSet {
+        event_index: game_state.event_index,
+        tiers,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferralTiersSet {
+    pub event_index: u64,
+    pub tiers: [u64; 2],
+    pub timestamp: u64,
+}
@@ -0,0 +1,53 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferralWindow<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_referral_window(ctx: Context<SetReferralWindow>, referral_window: u64) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Expiry is measured from each player's first referred buy, so a window changed
+    // mid-game would expire attributions retroactively: lock it with the fees
+    let now: u64 = current_timestamp()?;
+    require!(
+        now < game_state.premarket_end,
+        CustomErrors::PreMarketOver
+    );
+
+    // Validate inputs
+    check_referral_window(game_state, referral_window)?;
+
+    // Update state
+    game_state.referral_window = referral_window;
+
+    // Emit event
+    emit!(ReferralWindowSet {
+        event_index: game_state.event_index,
+        referral_window,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferralWindowSet {
+    pub event_index: u64,
+    pub referral_window: u64,
+    pub timestamp: u64,
+}
@@ -0,0 +1,108 @@
+use crate::{ state::*, error::* };
+use crate::engine;
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferrer<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + PlayerState::INIT_SPACE,
+        seeds = [player.key().as_ref(), PlayerState::SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub player_state: Box<Account<'info, PlayerState>>,
+
+    /// CHECK: Can be any account
+    #[account()]
+    pub referrer: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + ReferralStats::INIT_SPACE,
+        seeds = [ReferralStats::SEED, referrer.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub referrer_stats: Box<Account<'info, ReferralStats>>,
+
+    /// Statistics of the player's current referrer, checked in the handler
+    #[account(mut)]
+    pub previous_referrer_stats: Option<Box<Account<'info, ReferralStats>>>,
+
+    /// CHECK: Global profile of the referrer, which may not exist (checked with `is_registered`)
+    #[account(
+        seeds = [PlayerProfile::SEED, referrer.key().as_ref()],
+        bump
+    )]
+    pub referrer_profile: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_referrer(ctx: Context<SetReferrer>) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let player = ctx.accounts.player.key();
+    let referrer = ctx.accounts.referrer.key();
+
+    // Replace the referrer if the game's policy allows it
+    let now: u64 = current_timestamp()?;
+    let registered = is_registered(&ctx.accounts.referrer_profile);
+    let referrer_stats = &mut ctx.accounts.referrer_stats;
+
+    // The previous referrer's statistics must be theirs
+    let authority = ctx.accounts.authority.key();
+    if let Some(stats) = ctx.accounts.previous_referrer_stats.as_ref() {
+        check_pda(stats.key(), &[ReferralStats::SEED, player_state.current_referrer.as_ref(), authority.as_ref()], CustomErrors::InvalidReferrer)?;
+    }
+    let previous_stats = ctx.accounts.previous_referrer_stats.as_deref_mut().map(|stats| &mut **stats);
+    let previous_referrer = engine::set_referrer(
+        game_state,
+        player,
+        player_state,
+        referrer,
+        referrer_stats,
+        previous_stats,
+        registered,
+        now,
+    )?;
+
+    // Emit event
+    emit!(ReferrerSet {
+        event_index: game_state.event_index,
+        player,
+        previous_referrer,
+        referrer,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferrerSet {
+    pub event_index: u64,
+    pub player: Pubkey,
+    pub previous_referrer: Pubkey,
+    pub referrer: Pubkey,
+    pub timestamp: u64,
+}
@@ -0,0 +1,43 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferrerPolicy<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_referrer_policy(ctx: Context<SetReferrerPolicy>, policy: ReferrerPolicy) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Update state
+    game_state.referrer_policy = policy;
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(ReferrerPolicySet {
+        event_index: game_state.event_index,
+        policy,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct ReferrerPolicySet {
+    pub event_index: u64,
+    pub policy: ReferrerPolicy,
+    pub timestamp: u64,
+}
@@ -0,0 +1,49 @@
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetUsernameFee<'info> {
+    #[account(mut, address = game_state.authority)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+}
+
+pub fn set_username_fee(ctx: Context<SetUsernameFee>, fee: u64) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Validate inputs
+    require!(
+        fee <= MAX_USERNAME_FEE,
+        CustomErrors::InvalidUsernameFee
+    );
+
+    // Update state
+    game_state.username_fee = fee;
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(UsernameFeeSet {
+        event_index: game_state.event_index,
+        fee,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct UsernameFeeSet {
+    pub event_index: u64,
+    pub fee: u64,
+    pub timestamp: u64,
+}
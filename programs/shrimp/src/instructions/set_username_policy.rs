@@ -0,0 +1,42 @@
+use crate::state::*;
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetUsernamePolicy<'info> {
+    #[account(address = game_registry.owner)]
+    owner: Signer<'info>,
+
+    #[account(seeds = [GameRegistry::SEED], bump)]
+    pub game_registry: Account<'info, GameRegistry>,
+
+    #[account(
+        mut, 
+        seeds = [UsernamePolicy::SEED], 
+        bump
+    )]
+    pub username_policy: Account<'info, UsernamePolicy>,
+}
+
+pub fn set_username_policy(ctx: Context<SetUsernamePolicy>, rules: UsernameRules) -> Result<()> {
+    // Validate inputs
+    check_username_rules(&rules)?;
+
+    // Update state (usernames already claimed are kept)
+    ctx.accounts.username_policy.rules = rules;
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(UsernamePolicySet {
+        rules,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event]
+pub struct UsernamePolicySet {
+    pub rules: UsernameRules,
+    pub timestamp: u64,
+}
@@ -0,0 +1,117 @@
+use crate::engine;
+use crate::state::*;
+use crate::helpers::*;
+use crate::instructions::setup_game;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct StartSeason<'info> {
+    #[account(mut, address = game_registry.owner)]
+    owner: Signer<'info>,
+
+    /// Authority of the new season
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameRegistry::SEED], 
+        bump
+    )]
+    pub game_registry: Account<'info, GameRegistry>,
+
+    /// CHECK: Custom check
+    #[account(address = game_registry.authority)]
+    pub previous_authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, previous_authority.key().as_ref()], 
+        bump
+    )]
+    pub previous_game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        init, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump, 
+        payer = authority, 
+        space = 8 + GameState::INIT_SPACE
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+pub fn start_season(
+    ctx: Context<StartSeason>,
+    premarket_end: u64,
+    cooldown: u64,
+    fees: Option<FeeSchedule>,
+    curve: Option<CurveParams>,
+    prize_mode: Option<PrizeMode>,
+    carry_over: SeasonCarryOver,
+) -> Result<()> {
+    // Set up the new game with the devs and environment of the previous season
+    let authority = ctx.accounts.authority.key();
+    let previous_game_state = &mut ctx.accounts.previous_game_state;
+    let game_state = &mut ctx.accounts.game_state;
+    let devs = [previous_game_state.dev1, previous_game_state.dev2, previous_game_state.dev3];
+    setup_game(game_state, authority, devs, premarket_end, cooldown, previous_game_state.test_env, fees, curve, prize_mode)?;
+
+    // Make it the next season
+    engine::start_season(&mut ctx.accounts.game_registry, previous_game_state, game_state, carry_over)?;
+
+    // Fund each vault up to its rent
+    fund_vaults(
+        &ctx.accounts.authority,
+        [
+            &ctx.accounts.dev_vault,
+            &ctx.accounts.premarket_vault,
+            &ctx.accounts.payables_vault,
+            &ctx.accounts.pool_vault,
+        ],
+        &ctx.accounts.system_program,
+    )?;
+
+    // Emit event
+    let now: u64 = current_timestamp()?;
+    emit!(SeasonStarted {
+        season: game_state.season,
+        authority,
+        previous_authority: game_state.previous_authority,
+        premarket_end,
+        fees: game_state.fees,
+        curve: game_state.curve,
+        prize_mode: game_state.prize_mode,
+        carry_over,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event]
+pub struct SeasonStarted {
+    pub season: u64,
+    pub authority: Pubkey,
+    pub previous_authority: Pubkey,
+    pub premarket_end: u64,
+    pub fees: FeeSchedule,
+    pub curve: CurveParams,
+    pub prize_mode: PrizeMode,
+    pub carry_over: SeasonCarryOver,
+    pub timestamp: u64,
+}
@@ -0,0 +1,133 @@
+use crate::engine;
+use crate::{error::*, state::*};
+use crate::helpers::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SweepUnclaimed<'info> {
+    /// Anyone can sweep once the claim window has passed
+    pub caller: Signer<'info>,
+
+    /// CHECK: Custom check
+    #[account(address = game_state.authority)]
+    pub authority: AccountInfo<'info>,
+
+    #[account(
+        mut, 
+        seeds = [GameState::SEED, authority.key().as_ref()], 
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(mut, seeds = [DEV_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub dev_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PREMARKET_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub premarket_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [PAYABLES_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub payables_vault: SystemAccount<'info>,
+
+    #[account(mut, seeds = [POOL_VAULT_SEED, authority.key().as_ref()], bump)]
+    pub pool_vault: SystemAccount<'info>,
+
+    /// CHECK: Checked against the sweep destination
+    #[account()]
+    pub next_authority: Option<AccountInfo<'info>>,
+
+    /// Checked against the next authority in the handler
+    #[account(mut)]
+    pub next_game_state: Option<Box<Account<'info, GameState>>>,
+
+    /// Checked against the next authority in the handler
+    #[account(mut)]
+    pub next_pool_vault: Option<SystemAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>) -> Result<()> {
+    let game_state = &mut ctx.accounts.game_state;
+    let now: u64 = current_timestamp()?;
+
+    // Empty the unclaimed balances once the claim window has passed
+    let swept = engine::sweep_unclaimed(game_state, now)?;
+    let total = swept.total()?;
+
+    // Check the next season accounts against the policy
+    if let SweepDestination::NextSeason { authority } = game_state.sweep_destination {
+        require!(
+            ctx.accounts.next_authority.as_ref().map(|next| next.key()) == Some(authority),
+            CustomErrors::InvalidClaimPolicy
+        );
+        let next_game_state = ctx.accounts.next_game_state.as_ref().ok_or(CustomErrors::InvalidClaimPolicy)?;
+        let next_pool_vault = ctx.accounts.next_pool_vault.as_ref().ok_or(CustomErrors::InvalidClaimPolicy)?;
+        check_pda(next_game_state.key(), &[GameState::SEED, authority.as_ref()], CustomErrors::InvalidClaimPolicy)?;
+        check_pda(next_pool_vault.key(), &[POOL_VAULT_SEED, authority.as_ref()], CustomErrors::InvalidClaimPolicy)?;
+    }
+
+    // Credit the destination balance (the dev vault if the next season is over too)
+    let destination = engine::credit_sweep(game_state, ctx.accounts.next_game_state.as_deref_mut().map(|next| &mut **next), total)?;
+    let to = match destination {
+        SweepDestination::DevVault => ctx.accounts.dev_vault.to_account_info(),
+        SweepDestination::NextSeason { .. } => ctx.accounts.next_pool_vault.as_ref().ok_or(CustomErrors::InvalidClaimPolicy)?.to_account_info(),
+    };
+
+    // Move the lamports out of each vault
+    let authority = ctx.accounts.authority.key();
+    transfer_lamports_from_vault(
+        &ctx.accounts.premarket_vault,
+        &to,
+        &ctx.accounts.system_program,
+        PREMARKET_VAULT_SEED,
+        &authority,
+        ctx.bumps.premarket_vault,
+        swept.premarket,
+    )?;
+    transfer_lamports_from_vault(
+        &ctx.accounts.payables_vault,
+        &to,
+        &ctx.accounts.system_program,
+        PAYABLES_VAULT_SEED,
+        &authority,
+        ctx.bumps.payables_vault,
+        swept.payables,
+    )?;
+    transfer_lamports_from_vault(
+        &ctx.accounts.pool_vault,
+        &to,
+        &ctx.accounts.system_program,
+        POOL_VAULT_SEED,
+        &authority,
+        ctx.bumps.pool_vault,
+        swept.pool,
+    )?;
+
+    // Emit event
+    emit!(UnclaimedSwept {
+        event_index: game_state.event_index,
+        prize: swept.pool,
+        premarket: swept.premarket,
+        sell_and_ref: swept.payables,
+        destination,
+        timestamp: now,
+    });
+
+    // Update indexes
+    game_state.event_index = game_state.event_index.checked_add(1).ok_or(CustomErrors::MathOverflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct UnclaimedSwept {
+    pub event_index: u64,
+    /// Unclaimed prize (and rounding dust) swept from the pool vault.
+    pub prize: u64,
+    /// Unwithdrawn premarket earnings swept from the premarket vault.
+    pub premarket: u64,
+    /// Unwithdrawn sell and referral earnings swept from the payables vault.
+    pub sell_and_ref: u64,
+    pub destination: SweepDestination,
+    pub timestamp: u64,
+}
@@ -0,0 +1,34 @@
+use crate::state::*;
+use crate::helpers::*;
+use crate::instructions::ReservedNameSet;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct UnreserveUsername<'info> {
+    #[account(mut, address = game_registry.owner)]
+    owner: Signer<'info>,
+
+    #[account(seeds = [GameRegistry::SEED], bump)]
+    pub game_registry: Account<'info, GameRegistry>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [ReservedName::SEED, username.as_bytes()],
+        bump
+    )]
+    pub reserved_name: Account<'info, ReservedName>,
+}
+
+pub fn unreserve_username(_ctx: Context<UnreserveUsername>, username: String) -> Result<()> {
+    // Emit event (the reservation is closed and its rent refunded)
+    let now: u64 = current_timestamp()?;
+    emit!(ReservedNameSet {
+        username,
+        reserved: false,
+        timestamp: now,
+    });
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ pub mod instructions;
 pub mod state;
 pub mod helpers;
 pub mod account;
+pub mod engine;
 
 declare_id!("23BCUPpfPkfCu6bmPCaLgyTR8UkruWeUnEyeC5shr1mp");
 
@@ -14,9 +15,10 @@ declare_id!("23BCUPpfPkfCu6bmPCaLgyTR8UkruWeUnEyeC5shr1mp");
 pub mod shrimp {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
-        ctx: Context<Initialize>, dev1: Pubkey, dev2: Pubkey, dev3: Pubkey, premarket_end: u64, cooldown: u64, test_env: bool) -> Result<()> {
-        instructions::initialize(ctx, dev1, dev2, dev3, premarket_end, cooldown, test_env)
+        ctx: Context<Initialize>, dev1: Pubkey, dev2: Pubkey, dev3: Pubkey, premarket_end: u64, cooldown: u64, test_env: bool, fees: Option<state::FeeSchedule>, curve: Option<state::CurveParams>, prize_mode: Option<state::PrizeMode>) -> Result<()> {
+        instructions::initialize(ctx, dev1, dev2, dev3, premarket_end, cooldown, test_env, fees, curve, prize_mode)
     }
     
     pub fn set_collection(ctx: Context<SetCollection>) -> Result<()> {
@@ -27,6 +29,18 @@ pub mod shrimp {
         instructions::dev_withdraw(ctx)
     }
 
+    pub fn change_username(ctx: Context<ChangeUsername>, username: String) -> Result<()> {
+        instructions::change_username(ctx, username)
+    }
+
+    pub fn release_username(ctx: Context<ReleaseUsername>) -> Result<()> {
+        instructions::release_username(ctx)
+    }
+
+    pub fn carry_over_player(ctx: Context<CarryOverPlayer>) -> Result<()> {
+        instructions::carry_over_player(ctx)
+    }
+
     pub fn register(ctx: Context<Register>, username: String) -> Result<()> {
         instructions::register(ctx, username)
     }
@@ -35,20 +49,60 @@ pub mod shrimp {
         instructions::user_withdraw(ctx)
     }
 
-    pub fn sell_eggs(ctx: Context<SellAndHatchAccounts>) -> Result<()> {
-        instructions::sell_eggs(ctx)
+    pub fn sell_eggs(ctx: Context<SellAndHatchAccounts>, min_lamports_out: Option<u64>, valid_until: Option<u64>) -> Result<()> {
+        instructions::sell_eggs(ctx, min_lamports_out, valid_until)
+    }
+
+    pub fn hatch_eggs(ctx: Context<SellAndHatchAccounts>, valid_until: Option<u64>) -> Result<()> {
+        instructions::hatch_eggs(ctx, valid_until)
+    }
+
+    pub fn sell_eggs_partial(ctx: Context<SellAndHatchAccounts>, eggs: u128, min_lamports_out: Option<u64>, valid_until: Option<u64>) -> Result<()> {
+        instructions::sell_eggs_partial(ctx, eggs, min_lamports_out, valid_until)
+    }
+
+    pub fn hatch_and_sell(ctx: Context<SellAndHatchAccounts>, sell_bps: u16, min_lamports_out: Option<u64>, valid_until: Option<u64>) -> Result<()> {
+        instructions::hatch_and_sell(ctx, sell_bps, min_lamports_out, valid_until)
+    }
+
+    pub fn buy_shrimp(ctx: Context<BuyAccounts>, amount: u64, min_shrimp_out: Option<u128>, valid_until: Option<u64>) -> Result<()> {
+        instructions::buy_shrimp(ctx, amount, min_shrimp_out, valid_until)
+    }
+
+    pub fn buy_premarket(ctx: Context<BuyAccounts>, amount: u64, valid_until: Option<u64>) -> Result<()> {
+        instructions::buy_premarket(ctx, amount, valid_until)
+    }
+
+    pub fn buy_shrimp_by_username(ctx: Context<BuyByUsernameAccounts>, username: String, amount: u64, min_shrimp_out: Option<u128>, valid_until: Option<u64>) -> Result<()> {
+        instructions::buy_shrimp_by_username(ctx, username, amount, min_shrimp_out, valid_until)
+    }
+
+    pub fn buy_premarket_by_username(ctx: Context<BuyByUsernameAccounts>, username: String, amount: u64, valid_until: Option<u64>) -> Result<()> {
+        instructions::buy_premarket_by_username(ctx, username, amount, valid_until)
+    }
+
+    pub fn set_referrer(ctx: Context<SetReferrer>) -> Result<()> {
+        instructions::set_referrer(ctx)
+    }
+
+    pub fn set_referrer_policy(ctx: Context<SetReferrerPolicy>, policy: state::ReferrerPolicy) -> Result<()> {
+        instructions::set_referrer_policy(ctx, policy)
+    }
+
+    pub fn set_referral_split(ctx: Context<SetReferralSplit>, cashback: u64) -> Result<()> {
+        instructions::set_referral_split(ctx, cashback)
     }
 
-    pub fn hatch_eggs(ctx: Context<SellAndHatchAccounts>) -> Result<()> {
-        instructions::hatch_eggs(ctx)
+    pub fn set_referral_tiers(ctx: Context<SetReferralTiers>, tiers: [u64; 2]) -> Result<()> {
+        instructions::set_referral_tiers(ctx, tiers)
     }
 
-    pub fn buy_shrimp(ctx: Context<BuyAccounts>, amount: u64) -> Result<()> {
-        instructions::buy_shrimp(ctx, amount)
+    pub fn set_referral_guards(ctx: Context<SetReferralGuards>, guards: state::ReferralGuards) -> Result<()> {
+        instructions::set_referral_guards(ctx, guards)
     }
 
-    pub fn buy_premarket(ctx: Context<BuyAccounts>, amount: u64) -> Result<()> {
-        instructions::buy_premarket(ctx, amount)
+    pub fn set_referral_window(ctx: Context<SetReferralWindow>, referral_window: u64) -> Result<()> {
+        instructions::set_referral_window(ctx, referral_window)
     }
 
     pub fn set_market(ctx: Context<SetMarket>, market_eggs: u128) -> Result<()> {
@@ -63,6 +117,42 @@ pub mod shrimp {
         instructions::set_program_guards(ctx, max_ixs, program_whitelist)
     }
 
+    pub fn start_season(ctx: Context<StartSeason>, premarket_end: u64, cooldown: u64, fees: Option<state::FeeSchedule>, curve: Option<state::CurveParams>, prize_mode: Option<state::PrizeMode>, carry_over: state::SeasonCarryOver) -> Result<()> {
+        instructions::start_season(ctx, premarket_end, cooldown, fees, curve, prize_mode, carry_over)
+    }
+
+    pub fn set_fee_schedule(ctx: Context<SetFeeSchedule>, fees: state::FeeSchedule) -> Result<()> {
+        instructions::set_fee_schedule(ctx, fees)
+    }
+
+    pub fn finalize_game(ctx: Context<FinalizeGame>) -> Result<()> {
+        instructions::finalize_game(ctx)
+    }
+
+    pub fn set_username_policy(ctx: Context<SetUsernamePolicy>, rules: state::UsernameRules) -> Result<()> {
+        instructions::set_username_policy(ctx, rules)
+    }
+
+    pub fn reserve_username(ctx: Context<ReserveUsername>, username: String) -> Result<()> {
+        instructions::reserve_username(ctx, username)
+    }
+
+    pub fn unreserve_username(ctx: Context<UnreserveUsername>, username: String) -> Result<()> {
+        instructions::unreserve_username(ctx, username)
+    }
+
+    pub fn set_username_fee(ctx: Context<SetUsernameFee>, fee: u64) -> Result<()> {
+        instructions::set_username_fee(ctx, fee)
+    }
+
+    pub fn set_claim_policy(ctx: Context<SetClaimPolicy>, claim_window: u64, destination: state::SweepDestination) -> Result<()> {
+        instructions::set_claim_policy(ctx, claim_window, destination)
+    }
+
+    pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>) -> Result<()> {
+        instructions::sweep_unclaimed(ctx)
+    }
+
     pub fn end_premarket(ctx: Context<EndPremarket>) -> Result<()> {
         instructions::end_premarket(ctx)
     }
@@ -78,4 +168,16 @@ pub mod shrimp {
     pub fn admin_mint(ctx: Context<AdminMint>) -> Result<()> {
         instructions::admin_mint(ctx)
     }
+
+    pub fn quote_buy(ctx: Context<QuoteBuy>, amount: u64) -> Result<state::BuyQuote> {
+        instructions::quote_buy(ctx, amount)
+    }
+
+    pub fn quote_sell(ctx: Context<QuoteEggsAccounts>) -> Result<state::SellQuote> {
+        instructions::quote_sell(ctx)
+    }
+
+    pub fn quote_hatch(ctx: Context<QuoteEggsAccounts>) -> Result<state::HatchQuote> {
+        instructions::quote_hatch(ctx)
+    }
 }
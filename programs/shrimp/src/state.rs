@@ -1,10 +1,10 @@
 use anchor_lang::prelude::*;
 
-// Game Constants
+// Default curve constants (per game values live in `GameState::curve`)
 
 pub const PSN: u128 = 10000;
 pub const PSNH: u128 = 5000;
-pub const ENDGAME_LIMIT: u128 = (10 as u128).pow(34);
+pub const ENDGAME_LIMIT: u128 = 10u128.pow(34);
 
 pub const EGGS_TO_HATCH_1SHRIMP: u128 = 86400;  // Assuming 1 day's worth of seconds
 
@@ -12,7 +12,7 @@ pub const MARKET_START: u128 = 864000000000;    // 10 million eggs
 pub const NFT_MIN_BUY: u64 = 1000000000;        // 1 SOL minimum buy to get NFT
 pub const MIN_BUY: u64 = 10000000;              // 0.01 SOL minimum buy
 
-// Fee and bonus constants
+// Default fee and bonus constants (per game values live in `GameState::fees`)
 
 pub const DEV_FEE: u64 = 4;                     // 4% to devs
 pub const PREMARKET_FEE: u64 = 6;               // 6% to premarket
@@ -22,13 +22,171 @@ pub const REFERRAL_CASHBACK: u64 = 1;           // 1% cashback to referree
 pub const NFT_BONUS: u128 = 10;                 // 10% bonus for NFT holders
 pub const TESTNET_BONUS: u128 = 1;              // 1% bonus for testnet players
 
+// Fee schedule bounds
+
+pub const MAX_FEE: u64 = 20;                    // Dev + premarket fee at most 20%
+pub const MAX_REFERRAL: u64 = 10;               // Referral fee + cashback at most 10%
+pub const MAX_BONUS: u128 = 50;                 // Each production bonus at most 50%
+
+// Claim window after game over
+
+pub const CLAIM_WINDOW: u64 = 90 * 86400;       // Default: 90 days to withdraw after game over
+pub const MIN_CLAIM_WINDOW: u64 = 7 * 86400;    // At least 7 days outside test environments
+
+// Referral guards
+
+pub const MAX_MIN_REFERRER_SPEND: u64 = 100_000_000_000; // Referrer stake requirement at most 100 SOL
+
+// Referral attribution window
+
+pub const REFERRAL_WINDOW: u64 = 90 * 86400;    // Default: referrers earn for 90 days from a player's first referred buy
+pub const MIN_REFERRAL_WINDOW: u64 = 7 * 86400; // At least 7 days outside test environments
+pub const MAX_REFERRAL_WINDOW: u64 = 365 * 86400; // At most a year
+
+// Combined hatch and sell
+
+pub const SELL_BPS_MAX: u16 = 10000;            // `sell_bps` is out of 10000 (100%)
+
+// Username changes
+
+pub const USERNAME_COOLDOWN: u64 = 30 * 86400;  // 30 days between username changes or releases and a new claim
+pub const MAX_USERNAME_FEE: u64 = 1000000000;   // Change fee at most 1 SOL
+
 // Seed constants
 
 pub const CANDY_MACHINE_AUTHORITY_SEED: &str = "candy_machine";
 
+// Vault seeds (system-owned PDAs holding each treasury bucket, seeded with the authority)
+
+pub const DEV_VAULT_SEED: &[u8] = b"dev_vault";             // Backs `dev_balance`
+pub const PREMARKET_VAULT_SEED: &[u8] = b"premarket_vault"; // Backs `premarket_balance`
+pub const PAYABLES_VAULT_SEED: &[u8] = b"payables_vault";   // Backs `sell_and_ref_balance`
+pub const POOL_VAULT_SEED: &[u8] = b"pool_vault";           // Backs `pool_balance`
+
+// ───────────────────────── Curve Params ──────────────────────────
+// Bonding curve and game length captured per game at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct CurveParams {
+    pub psn: u128,               // Curve numerator weight
+    pub psnh: u128,              // Curve damping weight
+    pub eggs_to_hatch_1shrimp: u128, // Eggs per shrimp (and eggs a shrimp lays per day)
+    pub market_start: u128,      // Market eggs at initialize
+    pub endgame_limit: u128,     // Market eggs that end the game
+}
+
+impl Default for CurveParams {
+    fn default() -> Self {
+        Self {
+            psn: PSN,
+            psnh: PSNH,
+            eggs_to_hatch_1shrimp: EGGS_TO_HATCH_1SHRIMP,
+            market_start: MARKET_START,
+            endgame_limit: ENDGAME_LIMIT,
+        }
+    }
+}
+
+// ───────────────────────── Fee Schedule ──────────────────────────
+// Percentages captured per game at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct FeeSchedule {
+    pub dev_fee: u64,            // % of buys and sells to devs
+    pub premarket_fee: u64,      // % of buys and sells to premarket players
+    pub referral_fee: u64,       // % of referred buys to the referrer
+    pub referral_cashback: u64,  // % of referred buys back to the buyer
+    pub nft_bonus: u128,         // % production bonus for NFT holders
+    pub testnet_bonus: u128,     // % production bonus for testnet players
+}
+
+impl FeeSchedule {
+    // Total fee taken on buys and sells (dev + premarket)
+    pub fn trade_fee(&self) -> u64 {
+        self.dev_fee + self.premarket_fee
+    }
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            dev_fee: DEV_FEE,
+            premarket_fee: PREMARKET_FEE,
+            referral_fee: REFERRAL_FEE,
+            referral_cashback: REFERRAL_CASHBACK,
+            nft_bonus: NFT_BONUS,
+            testnet_bonus: TESTNET_BONUS,
+        }
+    }
+}
+
+// ────────────────────────── Prize Mode ───────────────────────────
+// How `final_balance` is shared out at game over, chosen per game at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Default)]
+pub enum PrizeMode {
+    #[default]
+    Premarket,                   // Pro rata to `premarket_spent` (premarket buyers only)
+    Shrimp,                      // Pro rata to shrimp owned at game over (`get_my_shrimp`)
+    MarketSpent,                 // Pro rata to lifetime `market_spent`
+}
+
+// ─────────────────────── Sweep Destination ───────────────────────
+// Where `sweep_unclaimed` moves the balances left after the claim window
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Default)]
+pub enum SweepDestination {
+    #[default]
+    DevVault,                    // Added to the dev balance
+    NextSeason { authority: Pubkey }, // Added to the pool of the game run by `authority`
+}
+
+// ─────────────────────── Referrer Policy ─────────────────────────
+// When `set_referrer` may replace a player's current referrer
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ReferrerPolicy {
+    #[default]
+    Locked,                      // Fixed once the player has made a purchase
+    After { days: u16 },         // Changeable once `days` have passed since it was last set
+    Free,                        // Changeable at any time
+}
+
+// ──────────────────────── Referral Guards ────────────────────────
+// Anti-sybil limits `process_referral` applies to every referred buy (0 = off)
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Default)]
+pub struct ReferralGuards {
+    pub min_referrer_spend: u64, // Lamports a referrer must have spent in the game to earn
+    pub referee_cap: u64,        // Lifetime referral fees a referrer can earn from one referee (0 = no cap)
+}
+
+// ──────────────────────── Username Rules ─────────────────────────
+// Characters and lengths `register` and `change_username` accept, kept in `UsernamePolicy`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct UsernameRules {
+    pub min_len: u8,             // Shortest username
+    pub max_len: u8,             // Longest username, at most `PlayerProfile::MAX_USERNAME_LENGTH`
+    pub allow_digits: bool,      // 0-9 allowed besides lowercase letters
+    pub allow_underscore: bool,  // _ allowed besides lowercase letters
+}
+
+impl Default for UsernameRules {
+    fn default() -> Self {
+        Self {
+            min_len: 1,
+            max_len: 12,
+            allow_digits: false,
+            allow_underscore: false,
+        }
+    }
+}
+
+// ─────────────────────── Season Carry-over ───────────────────────
+// What a season started with `start_season` takes over from the season before it
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Default)]
+pub struct SeasonCarryOver {
+    pub treasury: bool,          // Unclaimed balances of the previous season are swept into this pool
+    pub bonuses: bool,           // Previous NFTs keep their bonus, testnet players keep theirs (`carry_over_player`)
+}
+
 // ───────────────────────── Player State ──────────────────────────
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct PlayerState {
     // Production
     pub shrimp: u128,            // Current shrimp owned by the player
@@ -41,8 +199,11 @@ pub struct PlayerState {
 
     // Earnings (Lamports)
     pub referral_total: u64,     // Lifetime referral or cashback income
+    pub cashback_total: u64,     // Part of `referral_total` received as cashback on own buys
+    pub tier_total: u64,         // Lifetime income as second or third tier referrer
     pub sell_total: u64,         // Lifetime income from selling eggs
     pub referral_withdrawn: u64, // Lamports withdrawn from referral earnings
+    pub tier_withdrawn: u64,     // Lamports withdrawn from tier earnings
     pub sell_withdrawn: u64,     // Lamports withdrawn from sell earnings
     pub premarket_withdrawn: u64,// Lamports withdrawn from pre-market earnings
 
@@ -52,12 +213,17 @@ pub struct PlayerState {
 
     // Relationships
     pub current_referrer: Pubkey,// Address used as referrer for new purchases
+    pub referrer_set_at: u64,    // Timestamp `current_referrer` was last set
+    pub referee_cashback: u64,   // Cashback % this player gives their referees out of the referral budget (0 = game default)
+    pub referrer_earned: u64,    // Referral fees this player's buys have paid their referrers
+    pub referred_at: u64,        // First referred buy, kept across referrer changes (0 = none yet)
+    pub referral_expired: bool,  // The player's attribution window has closed
 
     // Status flags
     pub prize_withdrawn: bool,   // Whether the final-prize payout has been claimed
     pub minted: bool,            // True if the player’s NFT is already minted
     pub testnet_player: bool,    // Grants +1 % production if the player joined testnet
-    pub registered: bool,        // True if player has registered a username
+    pub registered: bool,        // True if player has registered in this game (username in `PlayerProfile`)
 }
 
 impl PlayerState {
@@ -66,7 +232,7 @@ impl PlayerState {
 
 // ────────────────────────── Game State ───────────────────────────
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct GameState {
     // Authority and dev accounts
     pub authority: Pubkey,       // Master authority PDA seed
@@ -81,6 +247,9 @@ pub struct GameState {
     // Economic parameters
     pub cooldown: u64,           // Minimum delay (sec) between hatch / sell
     pub market_eggs: u128,       // Eggs circulating in the open market
+    pub fees: FeeSchedule,       // Fee and bonus percentages for this game
+    pub curve: CurveParams,      // Bonding curve and game length for this game
+    pub prize_mode: PrizeMode,   // How the final balance is shared out
 
     // Premarket window
     pub premarket_end: u64,      // Timestamp when pre-market closes
@@ -88,10 +257,36 @@ pub struct GameState {
     pub premarket_balance: u64,  // Current treasury of pre-market earnings
     pub premarket_earned: u64,   // Lifetime pre-market earnings
 
-    // Balances (lamports)
+    // Balances (lamports, each held in its own vault above the vault's rent)
+    pub pool_balance: u64,       // Trading pool the curve prices against
     pub sell_and_ref_balance: u64,// Funds reserved for user sells & referrals
     pub dev_balance: u64,        // Accumulated dev fee balance
     pub final_balance: u64,      // Treasury for the end-game prize
+    pub prize_weight: u128,      // Sum of every player's prize weight, snapshot at game over
+
+    // Market totals
+    pub market_spent: u64,       // Global spending in the primary market
+    pub total_shrimp: u128,      // Shrimp bought and hatched (premarket shrimp excluded)
+
+    // Claims after game over
+    pub claim_window: u64,       // Seconds after game over that players can still withdraw
+    pub sweep_destination: SweepDestination, // Where unclaimed balances go after the window
+    pub game_over_at: u64,       // Timestamp the game ended (0 while it runs)
+    pub swept: bool,             // Unclaimed balances have been swept
+
+    // Usernames
+    pub username_fee: u64,       // Lamports paid into the pool by `change_username` (0 = free)
+
+    // Referrals
+    pub referrer_policy: ReferrerPolicy, // When players may change their referrer with `set_referrer`
+    pub referral_tiers: [u64; 2],// % of referred buys paid to the referrer's referrer and theirs (0 = off)
+    pub referral_guards: ReferralGuards, // Anti-sybil limits on referral income
+    pub referral_window: u64,    // Seconds a referrer earns from a player's first referred buy
+
+    // Seasons
+    pub season: u64,             // Season number in the `GameRegistry` (1 for the first game)
+    pub previous_authority: Pubkey, // Authority of the previous season (default for season 1)
+    pub carry_over: SeasonCarryOver, // What this season took over from the previous one
 
     // Game progression
     pub game_over: bool,         // Set to true once the game is concluded
@@ -122,7 +317,35 @@ impl LockState {
     pub const SEED: &'static [u8] = b"shrimplock";
 }
 
-// Usernames
+// Referral statistics of one referrer in one game
+#[account]
+#[derive(InitSpace, Default)]
+pub struct ReferralStats {
+    pub referrer: Pubkey,        // Player the statistics belong to
+    pub referees: u64,           // Players whose current referrer this is
+    pub referred_volume: u64,    // Lamports spent by referees on buys credited to this referrer
+    pub fees_earned: u64,        // Referral fees credited to this referrer
+    pub referee_cashback: u64,   // Cashback credited to referees on those buys
+}
+
+impl ReferralStats {
+    pub const SEED: &'static [u8] = b"referral_stats";
+}
+
+// Seasons
+#[account]
+#[derive(InitSpace)]
+pub struct GameRegistry {
+    pub owner: Pubkey,           // Owner allowed to start new seasons
+    pub season: u64,             // Number of the current season
+    pub authority: Pubkey,       // Authority of the current season's game
+}
+
+impl GameRegistry {
+    pub const SEED: &'static [u8] = b"game_registry";
+}
+
+// Usernames (global, shared by every game and season)
 #[account]
 #[derive(InitSpace)]
 pub struct UsernameToAddress {
@@ -135,14 +358,38 @@ impl UsernameToAddress {
 
 #[account]
 #[derive(InitSpace)]
-pub struct AddressToUsername {
-    #[max_len(12)]
-    pub username: String,
+pub struct PlayerProfile {
+    #[max_len(20)]
+    pub username: String,        // Claimed once by the first `register`
+    pub registered_at: u64,      // Timestamp the username was first claimed
+    pub name_changed_at: u64,    // Last username change or release (0 if never)
+    pub seasons_played: u64,     // Games the player has registered in
 }
 
-impl AddressToUsername {
-    pub const MAX_USERNAME_LENGTH: usize = 12;
-    pub const SEED: &'static [u8] = b"address_to_username";
+impl PlayerProfile {
+    pub const MAX_USERNAME_LENGTH: usize = 20;
+    pub const SEED: &'static [u8] = b"profile";
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UsernamePolicy {
+    pub rules: UsernameRules,    // Set by the registry owner with `set_username_policy`
+}
+
+impl UsernamePolicy {
+    pub const SEED: &'static [u8] = b"username_policy";
+}
+
+// Exists while a username is reserved by the registry owner and cannot be claimed
+#[account]
+#[derive(InitSpace)]
+pub struct ReservedName {
+    pub reserved_at: u64,
+}
+
+impl ReservedName {
+    pub const SEED: &'static [u8] = b"reserved_name";
 }
 
 #[account]
@@ -153,4 +400,35 @@ pub struct MinterState {
 
 impl MinterState {
     pub const SEED: &'static [u8] = b"minter";
-}
\ No newline at end of file
+}
+// ──────────────────────────── Quotes ─────────────────────────────
+// Returned by the quote instructions via return data
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct BuyQuote {
+    pub game_balance: u64,       // Game balance the buy is priced against
+    pub eggs: u128,              // Eggs bought after fees
+    pub shrimp: u128,            // Shrimp added to the player
+    pub dev_fee: u64,            // Lamports added to the dev balance
+    pub premarket_fee: u64,      // Lamports added to the premarket balance
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct SellQuote {
+    pub game_balance: u64,       // Game balance the sell is priced against
+    pub eggs: u128,              // Eggs sold, including bonus
+    pub eggs_forfeited: u128,    // Eggs past the endgame limit, which are not sold
+    pub bonus_percent: u8,       // NFT and testnet bonus applied to the eggs
+    pub sol_amount: u64,         // Gross value of the eggs
+    pub dev_fee: u64,            // Lamports added to the dev balance
+    pub premarket_fee: u64,      // Lamports added to the premarket balance
+    pub sol_received: u64,       // Lamports credited to the player after fees
+    pub ends_game: bool,         // Sell reaches past the endgame limit and ends the game
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct HatchQuote {
+    pub eggs: u128,              // Eggs hatched, including bonus
+    pub bonus_percent: u8,       // NFT and testnet bonus applied to the eggs
+    pub shrimp: u128,            // Shrimp added to the player
+}
@@ -0,0 +1,116 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use shrimp::state::PlayerState;
+
+/// What an agent does on its turn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Wait,
+    BuyPremarket(u64),
+    Buy(u64),
+    Sell,
+    SellPartial(u128),           // Eggs to sell (before bonuses), keeping the rest
+    Hatch,
+    HatchAndSell(u16),           // Share of the eggs to sell in basis points, hatching the rest
+    Withdraw,
+}
+
+/// Read-only view of the game handed to an agent on its turn
+pub struct AgentView<'a> {
+    pub step: u64,
+    pub now: u64,
+    pub premarket: bool,         // Premarket window is still open
+    pub game_balance: u64,
+    pub market_eggs: u128,
+    pub eggs: u128,              // The player's eggs right now (before bonuses)
+    pub player: &'a PlayerState,
+}
+
+pub trait Agent {
+    fn act(&mut self, view: &AgentView) -> Action;
+}
+
+/// Plays a fixed list of `(step, action)` pairs and waits otherwise
+pub struct ScriptedAgent {
+    script: Vec<(u64, Action)>,
+}
+
+impl ScriptedAgent {
+    pub fn new(script: Vec<(u64, Action)>) -> Self {
+        Self { script }
+    }
+}
+
+impl Agent for ScriptedAgent {
+    fn act(&mut self, view: &AgentView) -> Action {
+        self.script
+            .iter()
+            .find(|(step, _)| *step == view.step)
+            .map(|(_, action)| *action)
+            .unwrap_or(Action::Wait)
+    }
+}
+
+/// Picks an action at random each step with the configured weights
+pub struct RandomAgent {
+    rng: StdRng,
+    pub buy_chance: f64,         // Chance to buy on a given step
+    pub sell_chance: f64,        // Chance to sell on a given step
+    pub partial_sell_chance: f64, // Chance to sell half of the eggs on a given step
+    pub hatch_and_sell_chance: f64, // Chance to sell part of the eggs and hatch the rest on a given step
+    pub hatch_chance: f64,       // Chance to hatch on a given step
+    pub min_buy: u64,            // Lamports
+    pub max_buy: u64,            // Lamports
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64, min_buy: u64, max_buy: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            buy_chance: 0.1,
+            sell_chance: 0.05,
+            partial_sell_chance: 0.05,
+            hatch_and_sell_chance: 0.05,
+            hatch_chance: 0.2,
+            min_buy,
+            max_buy,
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn act(&mut self, view: &AgentView) -> Action {
+        let amount = self.rng.gen_range(self.min_buy..=self.max_buy);
+        let roll: f64 = self.rng.gen();
+
+        if view.premarket {
+            return if roll < self.buy_chance { Action::BuyPremarket(amount) } else { Action::Wait };
+        }
+
+        // Each action takes the next slice of the roll
+        let mut threshold = self.buy_chance;
+        if roll < threshold {
+            return Action::Buy(amount);
+        }
+        threshold += self.sell_chance;
+        if roll < threshold {
+            return Action::Sell;
+        }
+        threshold += self.partial_sell_chance;
+        if roll < threshold {
+            return Action::SellPartial(view.eggs / 2);
+        }
+        threshold += self.hatch_and_sell_chance;
+        if roll < threshold {
+            return Action::HatchAndSell(self.rng.gen_range(1_000..=9_000));
+        }
+        threshold += self.hatch_chance;
+        if roll < threshold {
+            Action::Hatch
+        } else if view.player.sell_total > view.player.sell_withdrawn {
+            Action::Withdraw
+        } else {
+            Action::Wait
+        }
+    }
+}
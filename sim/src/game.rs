@@ -0,0 +1,248 @@
+use anchor_lang::prelude::*;
+use shrimp::error::CustomErrors;
+use shrimp::engine::{self, Buyer, Referrer, Upline, VaultAmounts};
+use shrimp::state::*;
+
+/// A player in the simulation: their on-chain state plus wallet flows
+#[derive(Clone)]
+pub struct SimPlayer {
+    pub key: Pubkey,
+    pub state: PlayerState,
+    pub referral_stats: ReferralStats, // Statistics as a referrer
+    pub nft_holder: bool,        // Sells and hatches with the NFT bonus
+    pub spent: u64,              // Lamports sent to the game
+    pub received: u64,           // Lamports withdrawn from the game
+}
+
+/// Off-chain copy of a game: `GameState`, the vault lamports and every player.
+///
+/// Each action runs the program's `engine` rules, the same ones its instruction
+/// handlers apply, with the clock passed in as `now`.
+pub struct SimGame {
+    pub state: GameState,
+    pub vaults: VaultAmounts,    // Lamports held by each vault above its rent
+    pub players: Vec<SimPlayer>,
+}
+
+impl SimGame {
+    /// Mirrors `initialize` (and `set_referral_tiers`), with every vault empty above its rent
+    pub fn new(
+        premarket_end: u64,
+        cooldown: u64,
+        fees: FeeSchedule,
+        curve: CurveParams,
+        prize_mode: PrizeMode,
+        referral_tiers: [u64; 2],
+    ) -> Self {
+        let state = GameState {
+            market_eggs: curve.market_start,
+            fees,
+            curve,
+            prize_mode,
+            referral_tiers,
+            claim_window: CLAIM_WINDOW,
+            referral_window: REFERRAL_WINDOW,
+            season: 1,
+            event_index: 1,
+            game_index: 1,
+            premarket_end,
+            cooldown,
+            test_env: true,
+            max_ixs: 5,
+            ..Default::default()
+        };
+
+        Self {
+            state,
+            vaults: VaultAmounts::default(),
+            players: vec![],
+        }
+    }
+
+    pub fn add_player(&mut self, testnet_player: bool, nft_holder: bool) -> usize {
+        self.players.push(SimPlayer {
+            key: Pubkey::new_unique(),
+            state: PlayerState {
+                testnet_player,
+                ..Default::default()
+            },
+            referral_stats: ReferralStats::default(),
+            nft_holder,
+            spent: 0,
+            received: 0,
+        });
+        self.players.len() - 1
+    }
+
+    /// Run `f` like a transaction: a failed action leaves the game untouched
+    pub fn transact<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let snapshot = (self.state.clone(), self.vaults, self.players.clone());
+        let result = f(self);
+        if result.is_err() {
+            (self.state, self.vaults, self.players) = snapshot;
+        }
+        result
+    }
+
+    pub fn game_balance(&self) -> u64 {
+        self.state.pool_balance
+    }
+
+    pub fn buy_premarket(&mut self, player: usize, referrer: Option<usize>, amount: u64, now: u64) -> Result<()> {
+        let effects = self.with_buyer(player, referrer, |game, buyer| {
+            engine::buy_premarket(game, buyer, amount, now)
+        })?;
+        self.pay_in(player, &effects.deposits);
+        Ok(())
+    }
+
+    pub fn buy_shrimp(&mut self, player: usize, referrer: Option<usize>, amount: u64, now: u64) -> Result<()> {
+        let effects = self.with_buyer(player, referrer, |game, buyer| {
+            engine::buy_shrimp(game, buyer, amount, 0, now)
+        })?;
+        self.pay_in(player, &effects.deposits);
+        Ok(())
+    }
+
+    pub fn sell_eggs(&mut self, player: usize, now: u64) -> Result<()> {
+        let bonus_percent = self.bonus_percent(player);
+        let effects = engine::sell_eggs(&mut self.state, &mut self.players[player].state, bonus_percent, 0, now)?;
+        self.pay_out(&effects.from_pool)
+    }
+
+    pub fn sell_eggs_partial(&mut self, player: usize, eggs: u128, now: u64) -> Result<()> {
+        let bonus_percent = self.bonus_percent(player);
+        let effects = engine::sell_eggs_partial(&mut self.state, &mut self.players[player].state, eggs, bonus_percent, 0, now)?;
+        self.pay_out(&effects.from_pool)
+    }
+
+    pub fn hatch_and_sell(&mut self, player: usize, sell_bps: u16, now: u64) -> Result<()> {
+        let bonus_percent = self.bonus_percent(player);
+        let effects = engine::hatch_and_sell(&mut self.state, &mut self.players[player].state, sell_bps, bonus_percent, 0, now)?;
+        self.pay_out(&effects.sell.from_pool)
+    }
+
+    /// Mirrors the permissionless `finalize_game`
+    pub fn finalize_game(&mut self, now: u64) -> Result<()> {
+        engine::finalize_game(&mut self.state, now)
+    }
+
+    pub fn hatch_eggs(&mut self, player: usize, now: u64) -> Result<()> {
+        let bonus_percent = self.bonus_percent(player);
+        engine::hatch_eggs(&mut self.state, &mut self.players[player].state, bonus_percent, now)?;
+        Ok(())
+    }
+
+    /// Mirrors `user_withdraw`, returning the lamports paid out
+    pub fn user_withdraw(&mut self, player: usize, now: u64) -> Result<u64> {
+        let effects = engine::user_withdraw(&mut self.state, &mut self.players[player].state, now)?;
+        let sources = effects.sources;
+        self.vaults.premarket = self.vaults.premarket.checked_sub(sources.premarket).unwrap();
+        self.vaults.payables = self.vaults.payables.checked_sub(sources.payables).unwrap();
+        self.vaults.pool = self.vaults.pool.checked_sub(sources.pool).unwrap();
+        self.players[player].received = self.players[player].received.checked_add(effects.payout).unwrap();
+        Ok(effects.payout)
+    }
+
+    /// Lamports the player would receive from `user_withdraw` right now
+    pub fn withdrawable(&self, player: usize, now: u64) -> u64 {
+        let mut game_state = self.state.clone();
+        let mut player_state = self.players[player].state.clone();
+        engine::user_withdraw(&mut game_state, &mut player_state, now)
+            .map(|effects| effects.payout)
+            .unwrap_or_default()
+    }
+
+    /// Realized and withdrawable lamports minus lamports spent
+    pub fn pnl(&self, player: usize, now: u64) -> i128 {
+        let sim_player = &self.players[player];
+        sim_player.received as i128 + self.withdrawable(player, now) as i128 - sim_player.spent as i128
+    }
+
+    fn bonus_percent(&self, player: usize) -> u128 {
+        let sim_player = &self.players[player];
+        let mut bonus_percent = 0;
+        if sim_player.nft_holder {
+            bonus_percent += self.state.fees.nft_bonus;
+        }
+        if sim_player.state.testnet_player {
+            bonus_percent += self.state.fees.testnet_bonus;
+        }
+        bonus_percent
+    }
+
+    // The egg value of a sell moves from the pool to the other vaults
+    fn pay_out(&mut self, from_pool: &VaultAmounts) -> Result<()> {
+        self.vaults.pool = self.vaults.pool.checked_sub(from_pool.total()?).unwrap();
+        self.vaults.dev += from_pool.dev;
+        self.vaults.premarket += from_pool.premarket;
+        self.vaults.payables += from_pool.payables;
+        Ok(())
+    }
+
+    fn pay_in(&mut self, player: usize, deposits: &VaultAmounts) {
+        self.vaults.dev += deposits.dev;
+        self.vaults.premarket += deposits.premarket;
+        self.vaults.payables += deposits.payables;
+        self.vaults.pool += deposits.pool;
+        self.players[player].spent = self.players[player].spent.checked_add(deposits.total().unwrap()).unwrap();
+    }
+
+    /// Run `f` with the game and the buyer, borrowing the referrer's state and
+    /// their upline (the `current_referrer` chain) alongside
+    fn with_buyer<T>(
+        &mut self,
+        player: usize,
+        referrer: Option<usize>,
+        f: impl FnOnce(&mut GameState, Buyer) -> Result<T>,
+    ) -> Result<T> {
+        let key = self.players[player].key;
+        let Some(referrer) = referrer else {
+            let buyer = Buyer { key, payer: key, state: &mut self.players[player].state, referrer: None };
+            return f(&mut self.state, buyer);
+        };
+        require!(referrer != player, CustomErrors::InvalidReferrer);
+
+        // Follow the referrer's chain for the second and third tier. The chain stops
+        // at a player already in the buy, whom the engine would not pay again anyway.
+        let mut chain = vec![player, referrer];
+        while chain.len() < 4 {
+            let upline_key = self.players[chain[chain.len() - 1]].state.current_referrer;
+            match self.players.iter().position(|p| p.key == upline_key) {
+                Some(upline) if !chain.contains(&upline) => chain.push(upline),
+                _ => break,
+            }
+        }
+
+        // Borrow the buyer, the referrer and their upline at the same time
+        let mut borrowed: [Option<&mut SimPlayer>; 4] = [None, None, None, None];
+        for (index, sim_player) in self.players.iter_mut().enumerate() {
+            if let Some(slot) = chain.iter().position(|&i| i == index) {
+                borrowed[slot] = Some(sim_player);
+            }
+        }
+        let [Some(buyer), Some(referrer), second, third] = borrowed else {
+            unreachable!("the buyer and the referrer are always borrowed");
+        };
+
+        // Referrers are assumed to register a username once they meet the minimum buy
+        let registered = referrer.state.market_spent + referrer.state.premarket_spent >= MIN_BUY;
+
+        let buyer = Buyer {
+            key,
+            payer: key,
+            state: &mut buyer.state,
+            referrer: Some(Referrer {
+                key: referrer.key,
+                state: &mut referrer.state,
+                stats: &mut referrer.referral_stats,
+                registered,
+                upline: [
+                    second.map(|p| Upline { key: p.key, state: &mut p.state }),
+                    third.map(|p| Upline { key: p.key, state: &mut p.state }),
+                ],
+            }),
+        };
+        f(&mut self.state, buyer)
+    }
+}
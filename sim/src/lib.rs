@@ -0,0 +1,311 @@
+//! Offline economic simulator for Shrimp Farm games.
+//!
+//! - `game`: an off-chain copy of the program state driven by the program's `engine` rules
+//! - `agent`: scripted and randomized players
+//!
+//! A `Simulation` runs one game from `initialize` through the premarket and
+//! trading until the endgame limit trigger (or `max_steps`), writing one CSV
+//! row per step.
+
+pub mod agent;
+pub mod game;
+
+use std::io::{self, Write};
+
+use agent::{Action, Agent, AgentView};
+use game::SimGame;
+use shrimp::helpers::{check_curve_params, check_fee_schedule, check_referral_tiers, get_my_eggs_at};
+use shrimp::state::{CurveParams, FeeSchedule, PrizeMode};
+
+pub struct SimConfig {
+    pub start_time: u64,             // Unix timestamp of `initialize`
+    pub premarket_duration: u64,     // Seconds
+    pub cooldown: u64,               // Seconds between sells / hatches
+    pub step_seconds: u64,           // Clock advance per step
+    pub max_steps: u64,
+    pub initial_market_eggs: Option<u128>, // Same as `set_market` on a test game
+    pub fees: FeeSchedule,
+    pub curve: CurveParams,
+    pub prize_mode: PrizeMode,
+    pub referral_tiers: [u64; 2],    // Same as `set_referral_tiers`
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            start_time: 1_700_000_000,
+            premarket_duration: 86400,
+            cooldown: 3600,
+            step_seconds: 3600,
+            max_steps: 24 * 365,
+            initial_market_eggs: None,
+            fees: FeeSchedule::default(),
+            curve: CurveParams::default(),
+            prize_mode: PrizeMode::default(),
+            referral_tiers: [0, 0],
+        }
+    }
+}
+
+impl SimConfig {
+    /// The checks `initialize` (and `set_referral_tiers`) run on the same settings
+    pub fn check(&self) -> anchor_lang::Result<()> {
+        check_fee_schedule(&self.fees)?;
+        check_curve_params(&self.curve)?;
+        check_referral_tiers(&self.fees, self.referral_tiers)
+    }
+}
+
+/// An agent seated at a player slot, with its fixed referrer
+pub struct Seat {
+    pub agent: Box<dyn Agent>,
+    pub referrer: Option<usize>,
+}
+
+pub struct Simulation {
+    pub config: SimConfig,
+    pub game: SimGame,
+    pub seats: Vec<Seat>,
+    pub step: u64,
+    pub now: u64,
+    pub failed_actions: u64,     // Actions the program would have rejected
+}
+
+impl Simulation {
+    /// Fails on a config the program would reject, see `SimConfig::check`
+    pub fn new(config: SimConfig) -> anchor_lang::Result<Self> {
+        config.check()?;
+        let now = config.start_time;
+        let mut game = SimGame::new(
+            now + config.premarket_duration,
+            config.cooldown,
+            config.fees,
+            config.curve,
+            config.prize_mode,
+            config.referral_tiers,
+        );
+        if let Some(market_eggs) = config.initial_market_eggs {
+            game.state.market_eggs = market_eggs;
+        }
+
+        Ok(Self {
+            config,
+            game,
+            seats: vec![],
+            step: 0,
+            now,
+            failed_actions: 0,
+        })
+    }
+
+    /// Seat an agent as a new player and return its index
+    pub fn add_agent(
+        &mut self,
+        agent: Box<dyn Agent>,
+        referrer: Option<usize>,
+        testnet_player: bool,
+        nft_holder: bool,
+    ) -> usize {
+        let player = self.game.add_player(testnet_player, nft_holder);
+        self.seats.push(Seat { agent, referrer });
+        player
+    }
+
+    /// Advance the clock one step and let every agent act once, in seat order
+    pub fn step(&mut self) {
+        self.step += 1;
+        self.now += self.config.step_seconds;
+
+        for player in 0..self.seats.len() {
+            let view = AgentView {
+                step: self.step,
+                now: self.now,
+                premarket: self.now < self.game.state.premarket_end,
+                game_balance: self.game.game_balance(),
+                market_eggs: self.game.state.market_eggs,
+                eggs: get_my_eggs_at(&self.game.players[player].state, &self.game.state, self.now).unwrap_or_default(),
+                player: &self.game.players[player].state,
+            };
+            let action = self.seats[player].agent.act(&view);
+            if self.apply(player, action).is_err() {
+                self.failed_actions += 1;
+            }
+        }
+
+        // A keeper ends the game as soon as the market sits at the endgame limit
+        if self.game.state.market_eggs >= self.game.state.curve.endgame_limit {
+            let _ = self.game.finalize_game(self.now);
+        }
+    }
+
+    /// Run until the game ends or `max_steps`, then let every player withdraw.
+    /// Writes the CSV header and one row per step (including step 0) to `out`.
+    pub fn run(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.write_header(out)?;
+        self.write_row(out)?;
+
+        while !self.game.state.game_over && self.step < self.config.max_steps {
+            self.step();
+            self.write_row(out)?;
+        }
+
+        // Settle every player once the premarket is over
+        self.now = self.now.max(self.game.state.premarket_end + 1);
+        for player in 0..self.game.players.len() {
+            let _ = self.game.transact(|game| game.user_withdraw(player, self.now));
+        }
+        self.write_row(out)
+    }
+
+    fn apply(&mut self, player: usize, action: Action) -> anchor_lang::Result<()> {
+        let (referrer, now) = (self.seats[player].referrer, self.now);
+        self.game.transact(|game| match action {
+            Action::Wait => Ok(()),
+            Action::BuyPremarket(amount) => game.buy_premarket(player, referrer, amount, now),
+            Action::Buy(amount) => game.buy_shrimp(player, referrer, amount, now),
+            Action::Sell => game.sell_eggs(player, now),
+            Action::SellPartial(eggs) => game.sell_eggs_partial(player, eggs, now),
+            Action::Hatch => game.hatch_eggs(player, now),
+            Action::HatchAndSell(sell_bps) => game.hatch_and_sell(player, sell_bps, now),
+            Action::Withdraw => game.user_withdraw(player, now).map(|_| ()),
+        })
+    }
+
+    fn write_header(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "step,timestamp,market_eggs,game_balance,premarket_balance,dev_balance,sell_and_ref_balance,final_balance,game_over"
+        )?;
+        for player in 0..self.game.players.len() {
+            write!(out, ",p{}_pnl", player)?;
+        }
+        writeln!(out)
+    }
+
+    fn write_row(&self, out: &mut impl Write) -> io::Result<()> {
+        let state = &self.game.state;
+        write!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            self.step,
+            self.now,
+            state.market_eggs,
+            self.game.game_balance(),
+            state.premarket_balance,
+            state.dev_balance,
+            state.sell_and_ref_balance,
+            state.final_balance,
+            state.game_over,
+        )?;
+        for player in 0..self.game.players.len() {
+            write!(out, ",{}", self.game.pnl(player, self.now))?;
+        }
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent::{RandomAgent, ScriptedAgent};
+    use shrimp::state::MIN_BUY;
+
+    // A randomized game with a referral tree and tiered referral fees, as `main` runs it
+    fn run_game(seed: u64) -> (Simulation, String) {
+        let config = SimConfig { referral_tiers: [2, 1], max_steps: 5_000, ..Default::default() };
+        let mut sim = Simulation::new(config).unwrap();
+        for i in 0..10 {
+            let agent = RandomAgent::new(seed.wrapping_add(i), MIN_BUY, 100 * MIN_BUY);
+            sim.add_agent(Box::new(agent), (i > 0).then(|| (i as usize - 1) / 2), false, i % 5 == 0);
+        }
+
+        let mut out = vec![];
+        sim.run(&mut out).unwrap();
+        (sim, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn full_game_reaches_game_over_with_consistent_vaults() {
+        let (sim, csv) = run_game(7);
+        let mut lines = csv.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let column = |name: &str| header.iter().position(|c| *c == name).unwrap();
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert!(rows.iter().all(|row| row.len() == header.len()));
+
+        // Balances never go negative, and the game ends and stays over
+        for name in ["market_eggs", "game_balance", "premarket_balance", "dev_balance", "sell_and_ref_balance", "final_balance"] {
+            assert!(rows.iter().all(|row| row[column(name)].parse::<u128>().is_ok()), "{} went negative", name);
+        }
+        let game_over = column("game_over");
+        let ended = rows.iter().position(|row| row[game_over] == "true").expect("game never ended");
+        assert!(rows[ended..].iter().all(|row| row[game_over] == "true"));
+
+        // Every vault backs its balance, and no lamports appear or vanish
+        let (state, vaults) = (&sim.game.state, &sim.game.vaults);
+        assert_eq!(vaults.pool, state.pool_balance);
+        assert_eq!(vaults.dev, state.dev_balance);
+        assert_eq!(vaults.premarket, state.premarket_balance);
+        assert_eq!(vaults.payables, state.sell_and_ref_balance);
+        let spent: u64 = sim.game.players.iter().map(|p| p.spent).sum();
+        let received: u64 = sim.game.players.iter().map(|p| p.received).sum();
+        assert_eq!(spent, received + vaults.total().unwrap());
+
+        // The referral tree pays the second and third tiers
+        assert!(sim.game.players.iter().any(|p| p.state.tier_total > 0));
+    }
+
+    #[test]
+    fn scripted_agents_play_their_script() {
+        let config = SimConfig { max_steps: 48, ..Default::default() };
+        let mut sim = Simulation::new(config).unwrap();
+
+        // The premarket closes at step 24; the buy below the minimum is rejected
+        let referrer = sim.add_agent(
+            Box::new(ScriptedAgent::new(vec![(1, Action::BuyPremarket(10 * MIN_BUY)), (40, Action::Withdraw)])),
+            None,
+            false,
+            false,
+        );
+        let player = sim.add_agent(
+            Box::new(ScriptedAgent::new(vec![
+                (30, Action::Buy(10 * MIN_BUY)),
+                (32, Action::Hatch),
+                (34, Action::Sell),
+                (36, Action::Buy(MIN_BUY - 1)),
+            ])),
+            Some(referrer),
+            false,
+            false,
+        );
+
+        let mut out = vec![];
+        sim.run(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1 + 1 + 48 + 1);
+        assert_eq!(sim.failed_actions, 1);
+
+        let (referrer, player) = (&sim.game.players[referrer], &sim.game.players[player]);
+        assert_eq!(referrer.state.premarket_spent, 10 * MIN_BUY);
+        assert_eq!(player.state.market_spent, 10 * MIN_BUY);
+        assert_eq!(player.state.current_referrer, referrer.key);
+        assert!(player.state.sell_total > 0);
+        assert!(referrer.state.referral_total > 0);
+        assert_eq!(referrer.state.referral_withdrawn, referrer.state.referral_total);
+        assert_eq!(player.state.sell_withdrawn, player.state.sell_total);
+    }
+
+    #[test]
+    fn configs_the_program_rejects_do_not_run() {
+        let mut config = SimConfig::default();
+        config.curve.eggs_to_hatch_1shrimp = 0;
+        assert!(Simulation::new(config).is_err());
+
+        let mut config = SimConfig::default();
+        config.fees.dev_fee = 100;
+        assert!(Simulation::new(config).is_err());
+
+        let mut config = SimConfig::default();
+        config.referral_tiers = [config.fees.referral_fee, 1];
+        assert!(Simulation::new(config).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Run a randomized game and print the per-step CSV to stdout.
+//!
+//! Usage: shrimp-sim [--players N] [--steps N] [--seed N] [--step-seconds N]
+//!                   [--cooldown N] [--premarket N] [--market-eggs N]
+//!                   [--dev-fee N] [--premarket-fee N] [--referral-fee N] [--referral-cashback N]
+//!                   [--second-tier N] [--third-tier N]
+//!                   [--psn N] [--psnh N] [--eggs-to-hatch N] [--market-start N] [--endgame-limit N]
+//!                   [--prize-mode premarket|shrimp|market-spent]
+
+use std::io::{self, BufWriter};
+use std::process;
+
+use shrimp::helpers::{check_curve_params, check_fee_schedule, check_referral_tiers};
+use shrimp::state::{PrizeMode, MIN_BUY};
+use shrimp_sim::agent::RandomAgent;
+use shrimp_sim::{SimConfig, Simulation};
+
+fn parse<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    value.and_then(|v| v.parse().ok()).unwrap_or_else(|| {
+        eprintln!("{} expects a number", flag);
+        process::exit(2);
+    })
+}
+
+fn main() -> io::Result<()> {
+    let mut config = SimConfig::default();
+    let mut players: usize = 10;
+    let mut seed: u64 = 0;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--players" => players = parse(&flag, args.next()),
+            "--steps" => config.max_steps = parse(&flag, args.next()),
+            "--seed" => seed = parse(&flag, args.next()),
+            "--step-seconds" => config.step_seconds = parse(&flag, args.next()),
+            "--cooldown" => config.cooldown = parse(&flag, args.next()),
+            "--premarket" => config.premarket_duration = parse(&flag, args.next()),
+            "--market-eggs" => config.initial_market_eggs = Some(parse(&flag, args.next())),
+            "--dev-fee" => config.fees.dev_fee = parse(&flag, args.next()),
+            "--premarket-fee" => config.fees.premarket_fee = parse(&flag, args.next()),
+            "--referral-fee" => config.fees.referral_fee = parse(&flag, args.next()),
+            "--referral-cashback" => config.fees.referral_cashback = parse(&flag, args.next()),
+            "--second-tier" => config.referral_tiers[0] = parse(&flag, args.next()),
+            "--third-tier" => config.referral_tiers[1] = parse(&flag, args.next()),
+            "--psn" => config.curve.psn = parse(&flag, args.next()),
+            "--psnh" => config.curve.psnh = parse(&flag, args.next()),
+            "--eggs-to-hatch" => config.curve.eggs_to_hatch_1shrimp = parse(&flag, args.next()),
+            "--market-start" => config.curve.market_start = parse(&flag, args.next()),
+            "--endgame-limit" => config.curve.endgame_limit = parse(&flag, args.next()),
+            "--prize-mode" => config.prize_mode = match args.next().as_deref() {
+                Some("premarket") => PrizeMode::Premarket,
+                Some("shrimp") => PrizeMode::Shrimp,
+                Some("market-spent") => PrizeMode::MarketSpent,
+                _ => {
+                    eprintln!("--prize-mode expects premarket, shrimp or market-spent");
+                    process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("Unknown argument: {}", flag);
+                process::exit(2);
+            }
+        }
+    }
+
+    // Reject what `initialize` would, before the engine divides by zero or overflows
+    if check_fee_schedule(&config.fees).is_err() {
+        eprintln!("--dev-fee, --premarket-fee, --referral-fee and --referral-cashback must be within the program's bounds");
+        process::exit(2);
+    }
+    if check_curve_params(&config.curve).is_err() {
+        eprintln!("--psn, --psnh and --eggs-to-hatch must be positive and not overflow the curve, and --market-start below --endgame-limit");
+        process::exit(2);
+    }
+    if check_referral_tiers(&config.fees, config.referral_tiers).is_err() {
+        eprintln!("--second-tier and --third-tier must fit in the referral fee");
+        process::exit(2);
+    }
+
+    let mut sim = Simulation::new(config).expect("the config was checked");
+    for i in 0..players {
+        // Players form a referral tree: each is referred by the one at (i - 1) / 2
+        let referrer = (i > 0).then(|| (i - 1) / 2);
+        let agent = RandomAgent::new(seed.wrapping_add(i as u64), MIN_BUY, 100 * MIN_BUY);
+        sim.add_agent(Box::new(agent), referrer, false, i % 5 == 0);
+    }
+
+    sim.run(&mut BufWriter::new(io::stdout().lock()))
+}
@@ -88,6 +88,8 @@ describe("Shrimp Farm", () => {
   let dev1: Keypair;
   let dev2: Keypair;
   let dev3: Keypair;
+  let registryGame: Keypair;
+  let registryDevs: Keypair[];
 
   const createUmi = async () => {
     const RPC = "http://127.0.0.1:8899";
@@ -109,6 +111,19 @@ describe("Shrimp Farm", () => {
     anchor.setProvider(provider);
     wallet = provider.wallet as Wallet;
     program = anchor.workspace.Shrimp as Program<Shrimp>;
+
+    // The first game owns the registry as season 1; the per-test games leave it alone
+    registryGame = Keypair.generate();
+    registryDevs = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    await provider.connection.requestAirdrop(registryGame.publicKey, 1_000e9);
+    await new Promise(r => setTimeout(r, 500));
+    const owner = signerFromKeyFile(OWNER_KEY_FILE);
+    await program.methods
+      .initialize(registryDevs[0].publicKey, registryDevs[1].publicKey, registryDevs[2].publicKey,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60), new anchor.BN(5), true, null, null, null)
+      .accounts({ authority: registryGame.publicKey, owner: owner.publicKey })
+      .signers([registryGame, owner])
+      .rpc();
   });
 
   beforeEach(async () => {
@@ -157,6 +172,9 @@ describe("Shrimp Farm", () => {
         defaultPremarketEnd,
         new anchor.BN(5), // 5 s cooldown
         true,
+        null,
+        null,
+        null,
       )
       .accounts({ authority: authority.publicKey, owner: owner.publicKey })
       .signers([authority, owner])
@@ -166,7 +184,7 @@ describe("Shrimp Farm", () => {
   afterEach(async () => {
     await new Promise(r => setTimeout(r, 5000)); // try to stop local validator from crashing
     const gameState = await program.account.gameState.fetch(gameStateAccount);
-    if (gameState.devBalance.gt(new anchor.BN(5_296_560))) {
+    if (gameState.devBalance.gt(new anchor.BN(0))) {
       await program.methods
         .devWithdraw()
         .accounts({
@@ -200,21 +218,33 @@ describe("Shrimp Farm", () => {
     const additionalComputeIx =
       anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 });
 
-    // if caller says “no referrer”, substitute the null key
-    const refKey = ref ?? NULL_KEY;
+    // if caller says “no referrer”, fall back to the player's current referrer (if any)
+    let refKey = ref ?? NULL_KEY;
+    if (refKey.equals(NULL_KEY)) {
+      const playerState = await program.account.playerState.fetchNullable(
+        await utils.findPlayerDataAcc(from.publicKey, authority.publicKey));
+      refKey = playerState?.currentReferrer ?? NULL_KEY;
+    }
 
     // If not supplied, default to player
     const actualPayer = payer || from
 
+    const hasRef = !refKey.equals(NULL_KEY);
     const accs: any = {
       authority: authority.publicKey,
       payer: actualPayer.publicKey,
       player: from.publicKey,
-      referrer: refKey,
-      referrerState: await utils.findPlayerDataAcc(refKey, authority.publicKey),
+      referrer: hasRef ? refKey : null,
+      referrerState: hasRef ? await utils.findPlayerDataAcc(refKey, authority.publicKey) : null,
+      referrerStats: hasRef ? utils.findReferralStatsAcc(refKey, authority.publicKey, program.programId)[0] : null,
+      referrerProfile: hasRef ? utils.findProfileAcc(refKey, program.programId)[0] : null,
     };
 
-    const sig = await program.methods[method](new anchor.BN(amountLamports))
+    const call = method === "buyShrimp"
+      ? program.methods.buyShrimp(new anchor.BN(amountLamports), null, null)
+      : program.methods.buyPremarket(new anchor.BN(amountLamports), null);
+
+    const sig = await call
       .preInstructions([additionalComputeIx])
       .accounts(accs)
       .signers([from, actualPayer])
@@ -224,6 +254,21 @@ describe("Shrimp Farm", () => {
     return sig;
   };
 
+  // Accounts for set_referrer, including the statistics of the player's current referrer
+  const setReferrerAccounts = async (player: PublicKey, referrer: PublicKey, gameAuthority = authority.publicKey) => {
+    const playerState = await program.account.playerState.fetchNullable(
+      await utils.findPlayerDataAcc(player, gameAuthority));
+    const previous = playerState?.currentReferrer ?? NULL_KEY;
+    return {
+      player,
+      authority: gameAuthority,
+      referrer,
+      previousReferrerStats: previous.equals(NULL_KEY)
+        ? null
+        : utils.findReferralStatsAcc(previous, gameAuthority, program.programId)[0],
+    };
+  };
+
   // convenience wrappers hide the new detail from all tests
   const buyPremarket = (f, amt, ref = null, payer = null) => doBuy("buyPremarket", f, amt, ref, payer);
   const buyShrimp = (f, amt, ref = null, payer = null) => doBuy("buyShrimp", f, amt, ref, payer);
@@ -236,7 +281,7 @@ describe("Shrimp Farm", () => {
   ) => {
     const additionalComputeIx = anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 });
 
-    const tx = await program.methods.buyPremarket(amount)
+    const tx = await program.methods.buyPremarket(amount, null)
       .accounts({ player: from.publicKey, authority: authority.publicKey, referrer: ref })
       .instruction();
 
@@ -250,7 +295,7 @@ describe("Shrimp Farm", () => {
     await provider.connection.sendTransaction(multiTx, [from])
   };
 
-  const setupReferrer = async (refAccount, name = 'referrer') => {
+  const setupReferrer = async (refAccount, name = utils.uniqueUsername()) => {
     // Perform minimum buy
     const amount = new anchor.BN(1e7);
     await buyPremarket(refAccount, amount, NULL_KEY);
@@ -570,7 +615,7 @@ describe("Shrimp Farm", () => {
       const amount = new anchor.BN(1e8);
 
       await setupReferrer(refAccount);
-      await setupReferrer(randomAccount, 'referrerb');
+      await setupReferrer(randomAccount);
 
       // Buy premarket from player setting referrer
       await buyPremarket(wallet.payer, amount, randomAccount.publicKey, randomAccount);
@@ -581,7 +626,15 @@ describe("Shrimp Farm", () => {
         expect(player1.currentReferrer.toBase58()).to.equal(randomAccount.publicKey.toBase58());
       }
 
-      // Buy from player changing referrer
+      // A buy cannot change the referrer, set_referrer can while the policy allows it
+      await utils.shouldError(buyPremarket(wallet.payer, amount, refAccount.publicKey), "Referrer does not match the player's current referrer");
+      await program.methods.setReferrerPolicy({ free: {} })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      await program.methods.setReferrer()
+        .accounts(await setReferrerAccounts(wallet.publicKey, refAccount.publicKey))
+        .rpc();
       await buyPremarket(wallet.payer, amount, refAccount.publicKey);
 
       // Referrer should be set to to referrer
@@ -595,7 +648,7 @@ describe("Shrimp Farm", () => {
       const amount = new anchor.BN(1e8);
 
       await setupReferrer(refAccount);
-      await setupReferrer(refAccount2, 'referrerb');
+      await setupReferrer(refAccount2);
 
       // Buy premarket from player setting referrer
       await buyPremarket(wallet.payer, amount, refAccount.publicKey);
@@ -617,7 +670,7 @@ describe("Shrimp Farm", () => {
       const cashback = amount.toNumber() * 0.01;
 
       await setupReferrer(refAccount);
-      await setupReferrer(refAccount2, 'referrerb');
+      await setupReferrer(refAccount2);
 
       await buyPremarket(wallet.payer, amount, refAccount.publicKey);
       await advancePreMarket();
@@ -629,6 +682,67 @@ describe("Shrimp Farm", () => {
       expect(ref1State.referralTotal.toNumber()).to.equal(refBonus * 2);
       expect(player1.referralTotal.toNumber()).to.equal(cashback * 2);
     });
+
+    it("accepts referrers by their global profile", async () => {
+      const amount = new anchor.BN(1e8);
+
+      // Unregistered accounts cannot refer
+      await utils.shouldError(buyPremarket(wallet.payer, amount, refAccount.publicKey), "Invalid referrer");
+
+      // Registering in another game is enough to refer in this one
+      const otherAuthority = Keypair.generate();
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      await provider.connection.requestAirdrop(otherAuthority.publicKey, 1_000e9);
+      await new Promise(r => setTimeout(r, 500));
+      await program.methods
+        .initialize(dev1.publicKey, dev2.publicKey, dev3.publicKey, new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60), new anchor.BN(5), true, null, null, null)
+        .accounts({ authority: otherAuthority.publicKey, owner: owner.publicKey })
+        .signers([otherAuthority, owner])
+        .rpc();
+      await program.methods.buyPremarket(new anchor.BN(1e7), null)
+        .accounts({ payer: refAccount.publicKey, player: refAccount.publicKey, authority: otherAuthority.publicKey, referrer: null })
+        .signers([refAccount])
+        .rpc();
+      await program.methods.register(utils.uniqueUsername())
+        .accounts({ player: refAccount.publicKey, authority: otherAuthority.publicKey })
+        .signers([refAccount])
+        .rpc();
+
+      // The referrer still needs a state of their own in this game
+      await utils.shouldRevert(buyPremarket(wallet.payer, amount, refAccount.publicKey));
+      await buyPremarket(refAccount, new anchor.BN(1e7), NULL_KEY);
+
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      expect(refState.registered).to.equal(false);
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.04);
+    });
+
+    it("accepts referrers by username", async () => {
+      const amount = new anchor.BN(1e8);
+      const name = utils.uniqueUsername();
+      await setupReferrer(refAccount, name);
+
+      const buyByUsername = (username: string) => program.methods.buyPremarketByUsername(username, amount, null)
+        .accounts({
+          payer: wallet.publicKey,
+          player: wallet.publicKey,
+          authority: authority.publicKey,
+          referrerState: refStateAccount,
+          referrerStats: utils.findReferralStatsAcc(refAccount.publicKey, authority.publicKey, program.programId)[0],
+          referrerProfile: utils.findProfileAcc(refAccount.publicKey, program.programId)[0],
+        })
+        .rpc({ commitment: "confirmed" });
+
+      // Names nobody holds cannot refer
+      await utils.shouldRevert(buyByUsername(utils.uniqueUsername()));
+
+      await buyByUsername(name);
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      const player = await program.account.playerState.fetch(playerAccount);
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.04);
+      expect(player.currentReferrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+    });
   });
 
   describe("BuyPremarket events emit correct referrer", () => {
@@ -716,14 +830,21 @@ describe("Shrimp Farm", () => {
 
     it("updates to a new valid referrer", async () => {
       await setupReferrer(refAccount);
-      await setupReferrer(refAccount2, 'referrerb');
+      await setupReferrer(refAccount2);
 
       /* 1️⃣ give the player an initial referrer */
       await buyPremarket(wallet.payer, new anchor.BN(1e8), refAccount.publicKey);
       await advancePreMarket();
       await new Promise(r => setTimeout(r, 1_000));
 
-      /* 2️⃣ now supply a different valid referrer */
+      /* 2️⃣ switch to a different valid referrer, then buy with it */
+      await program.methods.setReferrerPolicy({ free: {} })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      await program.methods.setReferrer()
+        .accounts(await setReferrerAccounts(wallet.publicKey, refAccount2.publicKey))
+        .rpc();
       const sig = await buyShrimp(
         wallet.payer,
         new anchor.BN(1e8),
@@ -746,7 +867,7 @@ describe("Shrimp Farm", () => {
       await buyShrimp(wallet.payer, new anchor.BN(1e8), refAccount.publicKey);
       await new Promise(r => setTimeout(r, 1_000));
 
-      const sig = await program.methods.hatchEggs()
+      const sig = await program.methods.hatchEggs(null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc({ commitment: "confirmed" });
 
@@ -767,7 +888,7 @@ describe("Shrimp Farm", () => {
 
       await new Promise(r => setTimeout(r, 1_000));
 
-      const sig = await program.methods.hatchEggs()
+      const sig = await program.methods.hatchEggs(null)
         .accounts({ player: randomAccount.publicKey, authority: authority.publicKey, nftAsset: nft.publicKey })
         .signers([randomAccount])
         .rpc({ commitment: "confirmed" });
@@ -782,7 +903,7 @@ describe("Shrimp Farm", () => {
       await buyShrimp(wallet.payer, new anchor.BN(1e8), refAccount.publicKey);
       await new Promise(r => setTimeout(r, 1_000));
 
-      const sig = await program.methods.sellEggs()
+      const sig = await program.methods.sellEggs(null, null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc({ commitment: "confirmed" });
 
@@ -802,7 +923,7 @@ describe("Shrimp Farm", () => {
 
       await new Promise(r => setTimeout(r, 1_000));
 
-      const sig = await program.methods.sellEggs()
+      const sig = await program.methods.sellEggs(null, null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: nft.publicKey })
         .rpc({ commitment: "confirmed" });
 
@@ -833,10 +954,10 @@ describe("Shrimp Farm", () => {
 
       await new Promise(r => setTimeout(r, 1_000));
 
-      const sig1 = await program.methods.hatchEggs()
+      const sig1 = await program.methods.hatchEggs(null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc({ commitment: "confirmed" });
-      const sig2 = await program.methods.hatchEggs()
+      const sig2 = await program.methods.hatchEggs(null)
         .accounts({ player: bonusKp.publicKey, authority: authority.publicKey, nftAsset: null })
         .signers([bonusKp])
         .rpc({ commitment: "confirmed" });
@@ -857,13 +978,13 @@ describe("Shrimp Farm", () => {
       await buyShrimp(wallet.payer, new anchor.BN(1_000e8), refAccount.publicKey);
       await new Promise(r => setTimeout(r, 1_000));
 
-      await program.methods.hatchEggs()
+      await program.methods.hatchEggs(null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc();
 
       await new Promise(r => setTimeout(r, 500));
       await utils.shouldError(
-        program.methods.hatchEggs()
+        program.methods.hatchEggs(null)
           .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
           .rpc(),
         "Hatch on cooldown",
@@ -871,7 +992,7 @@ describe("Shrimp Farm", () => {
 
       await new Promise(r => setTimeout(r, 6_000));
 
-      await program.methods.hatchEggs()
+      await program.methods.hatchEggs(null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc();
     });
@@ -882,13 +1003,13 @@ describe("Shrimp Farm", () => {
       await buyShrimp(wallet.payer, new anchor.BN(1_000e8), refAccount.publicKey);
       await new Promise(r => setTimeout(r, 1_000));
 
-      await program.methods.sellEggs()
+      await program.methods.sellEggs(null, null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc();
 
       await new Promise(r => setTimeout(r, 500));
       await utils.shouldError(
-        program.methods.sellEggs()
+        program.methods.sellEggs(null, null)
           .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
           .rpc(),
         "Sell on cooldown",
@@ -896,7 +1017,7 @@ describe("Shrimp Farm", () => {
 
       await new Promise(r => setTimeout(r, 6_000));
 
-      await program.methods.sellEggs()
+      await program.methods.sellEggs(null, null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc();
     });
@@ -918,7 +1039,7 @@ describe("Shrimp Farm", () => {
       await provider.connection.requestAirdrop(refAccount.publicKey, 10e8);
       await new Promise(r => setTimeout(r, 500));
       await buyPremarket(refAccount, referrerPmSpend, NULL_KEY);
-      await program.methods.register('referrer')
+      await program.methods.register(utils.uniqueUsername())
         .accounts({ player: refAccount.publicKey, authority: authority.publicKey })
         .signers([refAccount])
         .rpc();
@@ -938,7 +1059,7 @@ describe("Shrimp Farm", () => {
       const spendB = lamports(3);   // 3 SOL
       const spendC = lamports(6);   // 6 SOL
 
-      /* initial dev balance already contains the PDA rent exemption */
+      /* dev balance starts at 0 (the dev vault only holds its rent) */
       const initialDev = (await program.account.gameState.fetch(gameStateAccount)).devBalance;
 
       /* ------------------------------------------------------------------ 1: pre‑market buys */
@@ -990,7 +1111,7 @@ describe("Shrimp Farm", () => {
         .signers([authority])
         .rpc();
 
-      await program.methods.sellEggs()
+      await program.methods.sellEggs(null, null)
         .accounts({ player: a.publicKey, authority: authority.publicKey, nftAsset: null })
         .signers([a])
         .rpc();
@@ -1017,18 +1138,13 @@ describe("Shrimp Farm", () => {
       // each player also receives their share of the pre‑market dividend pool (expPrem)
       const sharePrem = (spent: anchor.BN) => spent.mul(expPrem).div(totalPrem);
 
-      const expectA = share(spendA).add(sharePrem(spendA)).add(psA.referralTotal);
+      // A also gets the (tiny) value of the eggs that fit under the limit
+      const expectA = share(spendA).add(sharePrem(spendA)).add(psA.referralTotal).add(psA.sellTotal);
       const expectB = share(spendB).add(sharePrem(spendB)).add(psB.referralTotal);
       const expectC = share(spendC).add(sharePrem(spendC)).add(psC.referralTotal);
       const expectR = share(referrerPmSpend).add(sharePrem(referrerPmSpend)).add(psR.referralTotal);
 
-      /* rent buffer for the game PDA */
-      const rent = new anchor.BN(
-        await provider.connection.getMinimumBalanceForRentExemption(
-          (await provider.connection.getAccountInfo(gameStateAccount))!.data.length,
-        ),
-      );
-      const expectDevPayout = gameBefore.devBalance.sub(rent);
+      const expectDevPayout = gameBefore.devBalance;
 
       /* ------------------------------------------------------------------ 5: withdrawals */
       const withdrawDelta = async (kp: Keypair) => {
@@ -1071,16 +1187,28 @@ describe("Shrimp Farm", () => {
       );
       assertBnEq(deltaDev, expectDevPayout, "dev payout");
 
-      /* ------------------------------------------------------------------ 7: game account trimmed to rent */
+      /* ------------------------------------------------------------------ 7: game account and vaults trimmed to rent */
       const info = await provider.connection.getAccountInfo(gameStateAccount);
-      const rentLamports = await provider.connection.getMinimumBalanceForRentExemption(info!.data.length);
-      expect(info!.lamports).to.be.within(rentLamports, rentLamports + 10);
+      expect(info!.lamports).to.equal(await provider.connection.getMinimumBalanceForRentExemption(info!.data.length));
 
       const gameAfter = await program.account.gameState.fetch(gameStateAccount);
-      assertBnEq(gameAfter.devBalance, rent, "devBalance rent");
+      assertBnEq(gameAfter.devBalance, new anchor.BN(0), "devBalance cleared");
       assertBnEq(gameAfter.sellAndRefBalance, new anchor.BN(0), "sellAndRefBalance cleared");
       // HACK: this is wrong
       assertBnEq(gameAfter.premarketBalance, new anchor.BN(2), "premarketBalance cleared");
+
+      /* each vault holds its rent plus exactly its balance */
+      const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+      const vaultBalances: [string, anchor.BN][] = [
+        ["dev", gameAfter.devBalance],
+        ["premarket", gameAfter.premarketBalance],
+        ["payables", gameAfter.sellAndRefBalance],
+        ["pool", gameAfter.poolBalance],
+      ];
+      for (const [kind, balance] of vaultBalances) {
+        const vaultLamports = await provider.connection.getBalance(utils.findVault(kind, authority.publicKey));
+        assertBnEq(new anchor.BN(vaultLamports - vaultRent), balance, `${kind} vault`);
+      }
     });
 
     /* 7-B – every action reverts once game_over == true */
@@ -1096,7 +1224,7 @@ describe("Shrimp Farm", () => {
         .signers([authority])
         .rpc();
       await new Promise(r => setTimeout(r, 500));
-      await program.methods.sellEggs()
+      await program.methods.sellEggs(null, null)
         .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
         .rpc();
 
@@ -1107,18 +1235,50 @@ describe("Shrimp Farm", () => {
         err,
       );
       await utils.shouldError(
-        program.methods.hatchEggs()
+        program.methods.hatchEggs(null)
           .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
           .rpc(),
         err,
       );
       await utils.shouldError(
-        program.methods.sellEggs()
+        program.methods.sellEggs(null, null)
           .accounts({ player: wallet.publicKey, authority: authority.publicKey, nftAsset: null })
           .rpc(),
         err,
       );
     });
+
+    /* 7-C – GameOver event and permissionless finalize */
+    it("lets anyone finalize a game at the endgame limit and emits GameOver", async () => {
+      await buyPremarket(wallet.payer, new anchor.BN(1e9), NULL_KEY);
+      await advancePreMarket();
+
+      const finalize = () => program.methods.finalizeGame()
+        .accounts({ caller: randomAccount.publicKey, authority: authority.publicKey })
+        .signers([randomAccount])
+        .rpc({ commitment: "confirmed" });
+
+      await utils.shouldError(finalize(), "Market has not reached the endgame limit");
+
+      await program.methods.setMarket(ENDGAME)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      const sig = await finalize();
+
+      const game = await program.account.gameState.fetch(gameStateAccount);
+      expect(game.gameOver).to.equal(true);
+      expect(game.finalBalance.toString()).to.equal(game.poolBalance.toString());
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "gameOver");
+      expect(events).to.have.length(1);
+      expect((events[0].data as any).endedBy.toBase58()).to.equal(randomAccount.publicKey.toBase58());
+      expect((events[0].data as any).finalBalance.toString()).to.equal(game.finalBalance.toString());
+
+      await utils.shouldError(finalize(), "Game Over");
+    });
   });
 
   /* ------------------------------------------------------------------ 8 */
@@ -1134,7 +1294,7 @@ describe("Shrimp Farm", () => {
 
     it("registers usernames & enforces uniqueness", async () => {
       // valid, all-lowercase, 1-12 chars, letters only
-      const username = "testuser";
+      const username = utils.uniqueUsername();
       const username2 = "secondname";
 
       const player = Keypair.generate();
@@ -1152,11 +1312,7 @@ describe("Shrimp Farm", () => {
         .rpc();
 
       // username → address mapping exists
-      const [pda] = utils.findUsernameToAddressAcc(
-        username,
-        program.programId,
-        authority.publicKey,
-      );
+      const [pda] = utils.findUsernameToAddressAcc(username, program.programId);
       const mapping = await program.account.usernameToAddress.fetch(pda);
       expect(mapping.address.toString()).to.equal(player.publicKey.toString());
 
@@ -1186,6 +1342,98 @@ describe("Shrimp Farm", () => {
       );
     });
 
+    it("changes and releases usernames", async () => {
+      const [first, second] = [utils.uniqueUsername(), utils.uniqueUsername()];
+      const mapping = (name: string) => utils.findUsernameToAddressAcc(name, program.programId)[0];
+      const [profile] = utils.findProfileAcc(randomAccount.publicKey, program.programId);
+
+      await buyPremarket(randomAccount, new anchor.BN(1e7), NULL_KEY);
+      await program.methods.register(first)
+        .accounts({ player: randomAccount.publicKey, authority: authority.publicKey })
+        .signers([randomAccount])
+        .rpc();
+
+      // The change fee goes into the game pool
+      const fee = new anchor.BN(1e7);
+      await program.methods.setUsernameFee(fee)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      const change = (from: string, to: string) => program.methods.changeUsername(to)
+        .accounts({
+          player: randomAccount.publicKey,
+          authority: authority.publicKey,
+          oldUsernameToAddressAccount: mapping(from),
+          usernameToAddressAccount: mapping(to),
+        })
+        .signers([randomAccount])
+        .rpc();
+
+      const poolBefore = (await program.account.gameState.fetch(gameStateAccount)).poolBalance;
+      await change(first, second);
+      const poolAfter = (await program.account.gameState.fetch(gameStateAccount)).poolBalance;
+      expect(poolAfter.sub(poolBefore).toString()).to.equal(fee.toString());
+      expect((await program.account.playerProfile.fetch(profile)).username).to.equal(second);
+      expect(await provider.connection.getAccountInfo(mapping(first))).to.equal(null);
+
+      // Changes are rate limited
+      await utils.shouldError(change(second, utils.uniqueUsername()), "Username was changed too recently");
+
+      // Releasing frees the name for anyone
+      await program.methods.releaseUsername()
+        .accounts({ player: randomAccount.publicKey, usernameToAddressAccount: mapping(second) })
+        .signers([randomAccount])
+        .rpc();
+      expect((await program.account.playerProfile.fetch(profile)).username).to.equal("");
+      expect(await provider.connection.getAccountInfo(mapping(second))).to.equal(null);
+
+      await buyPremarket(randomAccount2, new anchor.BN(1e7), NULL_KEY);
+      await program.methods.register(second)
+        .accounts({ player: randomAccount2.publicKey, authority: authority.publicKey })
+        .signers([randomAccount2])
+        .rpc();
+      const [secondMapping] = utils.findUsernameToAddressAcc(second, program.programId);
+      expect((await program.account.usernameToAddress.fetch(secondMapping)).address.toBase58()).to.equal(randomAccount2.publicKey.toBase58());
+    });
+
+    it("follows the username policy and reserved names", async () => {
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      const setRules = (rules) => program.methods.setUsernamePolicy(rules)
+        .accounts({ owner: owner.publicKey })
+        .signers([owner])
+        .rpc();
+      const register = (player: Keypair, name: string) => program.methods.register(name)
+        .accounts({ player: player.publicKey, authority: authority.publicKey })
+        .signers([player])
+        .rpc();
+
+      await buyPremarket(randomAccount, new anchor.BN(1e7), NULL_KEY);
+      await buyPremarket(randomAccount2, new anchor.BN(1e7), NULL_KEY);
+
+      await setRules({ minLen: 3, maxLen: 16, allowDigits: true, allowUnderscore: true });
+      try {
+        // Digits and underscores are allowed, short names are not
+        await utils.shouldError(register(randomAccount, "ab"), "Invalid username");
+        await register(randomAccount, `${utils.uniqueUsername()}_42`);
+
+        // Reserved names cannot be claimed until released by the owner
+        const reserved = utils.uniqueUsername();
+        await program.methods.reserveUsername(reserved)
+          .accounts({ owner: owner.publicKey })
+          .signers([owner])
+          .rpc();
+        await utils.shouldError(register(randomAccount2, reserved), "Username is reserved");
+
+        await program.methods.unreserveUsername(reserved)
+          .accounts({ owner: owner.publicKey })
+          .signers([owner])
+          .rpc();
+        await register(randomAccount2, reserved);
+      } finally {
+        await setRules({ minLen: 1, maxLen: 12, allowDigits: false, allowUnderscore: false });
+      }
+    });
+
     it("rejects invalid usernames", async () => {
       const tooLong = "thirteenchars"; // 13 chars, lowercase letters
       const special = "user@name";     // contains '@'
@@ -1282,6 +1530,827 @@ describe("Shrimp Farm", () => {
     });
   });
 
+  /* ------------------------------------------------------------------ 9 */
+  describe("Quotes", () => {
+    it("quoteBuy matches the shrimp added by buyShrimp", async () => {
+      await buyPremarket(randomAccount, new anchor.BN(1e9), NULL_KEY);
+      await advancePreMarket();
+
+      const amount = new anchor.BN(1e8);
+      const quote = await program.methods.quoteBuy(amount)
+        .accounts({ authority: authority.publicKey })
+        .view();
+
+      await buyShrimp(wallet.payer, amount, NULL_KEY);
+
+      const playerState = await program.account.playerState.fetch(playerAccount);
+      expect(playerState.shrimp.toString()).to.equal(quote.shrimp.toString());
+    });
+
+    it("quoteSell and quoteHatch price the player's eggs", async () => {
+      await advancePreMarket();
+      await buyShrimp(wallet.payer, new anchor.BN(1e9), NULL_KEY);
+      await new Promise(r => setTimeout(r, 2_000));
+
+      const accounts = { player: wallet.publicKey, authority: authority.publicKey, nftAsset: null };
+      const sellQuote = await program.methods.quoteSell().accounts(accounts).view();
+      const hatchQuote = await program.methods.quoteHatch().accounts(accounts).view();
+
+      expect(sellQuote.endsGame).to.be.false;
+      expect(sellQuote.solReceived.gt(new anchor.BN(0))).to.be.true;
+      expect(sellQuote.solAmount.toString()).to.equal(
+        sellQuote.solReceived.add(sellQuote.devFee).add(sellQuote.premarketFee).toString()
+      );
+      expect(hatchQuote.shrimp.gt(new anchor.BN(0))).to.be.true;
+    });
+  });
+
+  describe("Slippage", () => {
+    it("rejects buys and sells below the requested minimum", async () => {
+      await advancePreMarket();
+      await buyShrimp(wallet.payer, new anchor.BN(1e9), NULL_KEY);
+
+      const amount = new anchor.BN(1e8);
+      const buyQuote = await program.methods.quoteBuy(amount)
+        .accounts({ authority: authority.publicKey })
+        .view();
+
+      await utils.shouldError(
+        program.methods.buyShrimp(amount, buyQuote.shrimp.add(new anchor.BN(1)), null)
+          .accounts({ payer: randomAccount.publicKey, player: randomAccount.publicKey, authority: authority.publicKey, referrer: null })
+          .signers([randomAccount])
+          .rpc(),
+        "Output below the requested minimum",
+      );
+
+      await new Promise(r => setTimeout(r, 2_000));
+      const accounts = { player: wallet.publicKey, authority: authority.publicKey, nftAsset: null };
+      await utils.shouldError(
+        program.methods.sellEggs(new anchor.BN("18446744073709551615"), null).accounts(accounts).rpc(),
+        "Output below the requested minimum",
+      );
+      await program.methods.sellEggs(new anchor.BN(1), null).accounts(accounts).rpc();
+    });
+  });
+
+  describe("Deadlines", () => {
+    it("rejects orders that land after valid_until", async () => {
+      const past = new anchor.BN(Math.floor(Date.now() / 1000) - 60);
+      const future = new anchor.BN(Math.floor(Date.now() / 1000) + 60);
+      const accounts = { payer: wallet.publicKey, player: wallet.publicKey, authority: authority.publicKey, referrer: null };
+
+      await utils.shouldError(
+        program.methods.buyPremarket(new anchor.BN(1e8), past).accounts(accounts).rpc(),
+        "Transaction expired",
+      );
+      await program.methods.buyPremarket(new anchor.BN(1e8), future).accounts(accounts).rpc();
+
+      await advancePreMarket();
+      await utils.shouldError(
+        program.methods.buyShrimp(new anchor.BN(1e8), null, past).accounts(accounts).rpc(),
+        "Transaction expired",
+      );
+
+      const sellAccounts = { player: wallet.publicKey, authority: authority.publicKey, nftAsset: null };
+      await utils.shouldError(
+        program.methods.hatchEggs(past).accounts(sellAccounts).rpc(),
+        "Transaction expired",
+      );
+      await utils.shouldError(
+        program.methods.sellEggs(null, past).accounts(sellAccounts).rpc(),
+        "Transaction expired",
+      );
+    });
+  });
+
+  describe("Fee schedule", () => {
+    const fees = {
+      devFee: new anchor.BN(2),
+      premarketFee: new anchor.BN(3),
+      referralFee: new anchor.BN(4),
+      referralCashback: new anchor.BN(1),
+      nftBonus: new anchor.BN(10),
+      testnetBonus: new anchor.BN(1),
+    };
+
+    it("starts with the default fees and can be changed before premarket ends", async () => {
+      let gameState = await program.account.gameState.fetch(gameStateAccount);
+      expect(gameState.fees.devFee.toNumber()).to.equal(4);
+      expect(gameState.fees.premarketFee.toNumber()).to.equal(6);
+
+      await program.methods.setFeeSchedule(fees)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      gameState = await program.account.gameState.fetch(gameStateAccount);
+      expect(gameState.fees.devFee.toNumber()).to.equal(2);
+      expect(gameState.fees.premarketFee.toNumber()).to.equal(3);
+
+      // Dev fee on a premarket buy follows the new schedule
+      const devBalanceBefore = gameState.devBalance;
+      await buyPremarket(randomAccount, new anchor.BN(1e9), NULL_KEY);
+      gameState = await program.account.gameState.fetch(gameStateAccount);
+      expect(gameState.devBalance.sub(devBalanceBefore).toNumber()).to.equal(2e7);
+    });
+
+    it("rejects fees above the bounds", async () => {
+      await utils.shouldError(
+        program.methods.setFeeSchedule({ ...fees, devFee: new anchor.BN(15), premarketFee: new anchor.BN(10) })
+          .accounts({ authority: authority.publicKey })
+          .signers([authority])
+          .rpc(),
+        "Fee schedule exceeds the allowed bounds",
+      );
+    });
+
+    it("locks the fees once premarket ends", async () => {
+      await advancePreMarket();
+      await utils.shouldError(
+        program.methods.setFeeSchedule(fees)
+          .accounts({ authority: authority.publicKey })
+          .signers([authority])
+          .rpc(),
+        "Premarket is over",
+      );
+    });
+  });
+
+  describe("Curve parameters", () => {
+    const initWithCurve = async (curve) => {
+      const newAuthority = Keypair.generate();
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      await provider.connection.requestAirdrop(newAuthority.publicKey, 1_000e9);
+      await new Promise(r => setTimeout(r, 500));
+
+      await program.methods
+        .initialize(
+          dev1.publicKey,
+          dev2.publicKey,
+          dev3.publicKey,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60),
+          new anchor.BN(5),
+          true,
+          null,
+          curve,
+          null,
+        )
+        .accounts({ authority: newAuthority.publicKey, owner: owner.publicKey })
+        .signers([newAuthority, owner])
+        .rpc();
+      return utils.findGameDataAcc(newAuthority.publicKey);
+    };
+
+    const curve = {
+      psn: PSN,
+      psnh: PSNH,
+      eggsToHatch1Shrimp: new anchor.BN(3_600),
+      marketStart: new anchor.BN(36_000_000_000),
+      endgameLimit: new anchor.BN("10000000000000000000000000000"),
+    };
+
+    it("stores the curve and starts the market from it", async () => {
+      const gameState = await program.account.gameState.fetch(await initWithCurve(curve));
+      expect(gameState.curve.eggsToHatch1Shrimp.toNumber()).to.equal(3_600);
+      expect(gameState.marketEggs.toString()).to.equal(curve.marketStart.toString());
+    });
+
+    it("rejects curves that could overflow", async () => {
+      await utils.shouldError(
+        initWithCurve({ ...curve, psn: new anchor.BN("10000000000000000000000000000") }),
+        "Curve parameters are invalid or could overflow",
+      );
+    });
+  });
+
+  describe("Vaults", () => {
+    it("keeps each bucket in its own vault and ignores stray lamports", async () => {
+      await buyPremarket(wallet.payer, new anchor.BN(1e9), NULL_KEY);
+      await advancePreMarket();
+      await buyShrimp(wallet.payer, new anchor.BN(1e9), NULL_KEY);
+
+      // Every vault holds its rent plus the balance it backs
+      const game = await program.account.gameState.fetch(gameStateAccount);
+      const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+      const poolVault = utils.findVault("pool", authority.publicKey);
+      expect(await provider.connection.getBalance(poolVault) - vaultRent).to.equal(game.poolBalance.toNumber());
+      expect(await provider.connection.getBalance(utils.findVault("dev", authority.publicKey)) - vaultRent)
+        .to.equal(game.devBalance.toNumber());
+      expect(await provider.connection.getBalance(utils.findVault("premarket", authority.publicKey)) - vaultRent)
+        .to.equal(game.premarketBalance.toNumber());
+
+      // Lamports sent straight to the pool vault don't move the price
+      const amount = new anchor.BN(1e8);
+      const before = await program.methods.quoteBuy(amount).accounts({ authority: authority.publicKey }).view();
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({ fromPubkey: wallet.publicKey, toPubkey: poolVault, lamports: 1e9 }),
+      ));
+      const after = await program.methods.quoteBuy(amount).accounts({ authority: authority.publicKey }).view();
+      expect(after.gameBalance.toNumber()).to.equal(before.gameBalance.toNumber());
+      expect(after.shrimp.toString()).to.equal(before.shrimp.toString());
+    });
+  });
+
+  describe("Prize modes", () => {
+    it("shares the final balance by shrimp with market buyers", async () => {
+      const newAuthority = Keypair.generate();
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      await provider.connection.requestAirdrop(newAuthority.publicKey, 1_000e9);
+      await new Promise(r => setTimeout(r, 500));
+
+      await program.methods
+        .initialize(
+          dev1.publicKey,
+          dev2.publicKey,
+          dev3.publicKey,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60),
+          new anchor.BN(5),
+          true,
+          null,
+          null,
+          { shrimp: {} },
+        )
+        .accounts({ authority: newAuthority.publicKey, owner: owner.publicKey })
+        .signers([newAuthority, owner])
+        .rpc();
+      const game = utils.findGameDataAcc(newAuthority.publicKey);
+
+      // One premarket buyer and one market buyer
+      await program.methods.buyPremarket(new anchor.BN(1e9), null)
+        .accounts({ payer: wallet.publicKey, player: wallet.publicKey, authority: newAuthority.publicKey, referrer: null })
+        .rpc();
+      await program.methods.endPremarket()
+        .accounts({ authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      await program.methods.buyShrimp(new anchor.BN(1e9), null, null)
+        .accounts({ payer: randomAccount2.publicKey, player: randomAccount2.publicKey, authority: newAuthority.publicKey, referrer: null })
+        .signers([randomAccount2])
+        .rpc();
+
+      // Reach the endgame limit and settle
+      await program.methods.setMarket(new anchor.BN(10).pow(new anchor.BN(34)))
+        .accounts({ authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      await program.methods.finalizeGame()
+        .accounts({ caller: wallet.publicKey, authority: newAuthority.publicKey })
+        .rpc();
+
+      // The market buyer claims a share by shrimp owned
+      const gameState = await program.account.gameState.fetch(game);
+      const playerState = await program.account.playerState.fetch(utils.findPlayerDataAcc(randomAccount2.publicKey, newAuthority.publicKey));
+      expect(gameState.prizeMode).to.deep.equal({ shrimp: {} });
+      const expected = gameState.finalBalance.mul(playerState.shrimp).div(gameState.prizeWeight);
+      expect(expected.toNumber()).to.be.greaterThan(0);
+
+      const before = await provider.connection.getBalance(randomAccount2.publicKey);
+      await program.methods.userWithdraw()
+        .accounts({ player: randomAccount2.publicKey, authority: newAuthority.publicKey })
+        .signers([randomAccount2])
+        .rpc();
+      const after = await provider.connection.getBalance(randomAccount2.publicKey);
+      expect(after - before).to.equal(expected.toNumber());
+    });
+  });
+
+  describe("Claim window", () => {
+    it("sweeps unclaimed balances to the dev vault once the window closes", async () => {
+      const newAuthority = Keypair.generate();
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      await provider.connection.requestAirdrop(newAuthority.publicKey, 1_000e9);
+      await new Promise(r => setTimeout(r, 500));
+
+      await program.methods
+        .initialize(
+          dev1.publicKey,
+          dev2.publicKey,
+          dev3.publicKey,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60),
+          new anchor.BN(5),
+          true,
+          null,
+          null,
+          null,
+        )
+        .accounts({ authority: newAuthority.publicKey, owner: owner.publicKey })
+        .signers([newAuthority, owner])
+        .rpc();
+      const game = utils.findGameDataAcc(newAuthority.publicKey);
+
+      // A one second window is only allowed in the test environment
+      await program.methods.setClaimPolicy(new anchor.BN(1), { devVault: {} })
+        .accounts({ authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+
+      await program.methods.buyPremarket(new anchor.BN(1e9), null)
+        .accounts({ payer: wallet.publicKey, player: wallet.publicKey, authority: newAuthority.publicKey, referrer: null })
+        .rpc();
+      await program.methods.endPremarket()
+        .accounts({ authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      await program.methods.setMarket(new anchor.BN(10).pow(new anchor.BN(34)))
+        .accounts({ authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      await program.methods.finalizeGame()
+        .accounts({ caller: wallet.publicKey, authority: newAuthority.publicKey })
+        .rpc();
+
+      const sweep = () => program.methods.sweepUnclaimed()
+        .accounts({
+          caller: randomAccount2.publicKey,
+          authority: newAuthority.publicKey,
+          nextAuthority: null,
+          nextGameState: null,
+          nextPoolVault: null,
+        })
+        .signers([randomAccount2])
+        .rpc({ commitment: "confirmed" });
+
+      await new Promise(r => setTimeout(r, 3_000));
+
+      // Nobody claimed, so the premarket earnings and the prize are swept
+      const before = await program.account.gameState.fetch(game);
+      const unclaimed = before.premarketBalance.add(before.sellAndRefBalance).add(before.poolBalance);
+      const devVault = utils.findVault("dev", newAuthority.publicKey);
+      const devLamports = await provider.connection.getBalance(devVault);
+      const sig = await sweep();
+
+      const after = await program.account.gameState.fetch(game);
+      expect(after.swept).to.equal(true);
+      expect(after.poolBalance.toNumber()).to.equal(0);
+      expect(after.premarketBalance.toNumber()).to.equal(0);
+      expect(after.devBalance.toString()).to.equal(before.devBalance.add(unclaimed).toString());
+      expect(await provider.connection.getBalance(devVault)).to.equal(devLamports + unclaimed.toNumber());
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "unclaimedSwept");
+      expect(events).to.have.length(1);
+      expect((events[0].data as any).prize.toString()).to.equal(before.poolBalance.toString());
+
+      // The window is closed to players and the sweep happens once
+      await utils.shouldError(program.methods.userWithdraw()
+        .accounts({ player: wallet.publicKey, authority: newAuthority.publicKey })
+        .rpc(), "Claim window is closed");
+      await utils.shouldError(sweep(), "Unclaimed balances already swept");
+    });
+  });
+
+  describe("Seasons", () => {
+    it("starts the next season once the current one is over", async () => {
+      const seasonOne = registryGame;
+      const seasonTwo = Keypair.generate();
+      const owner = signerFromKeyFile(OWNER_KEY_FILE);
+      await provider.connection.requestAirdrop(seasonTwo.publicKey, 1_000e9);
+      await new Promise(r => setTimeout(r, 500));
+      const premarketEnd = new anchor.BN(Math.floor(Date.now() / 1000) + 72 * 60 * 60);
+      const [registry] = PublicKey.findProgramAddressSync([Buffer.from("game_registry")], program.programId);
+
+      // The first game is season 1 of the registry, and later games did not take it over
+      const registryBefore = await program.account.gameRegistry.fetch(registry);
+      expect(registryBefore.season.toNumber()).to.equal(1);
+      expect(registryBefore.authority.toBase58()).to.equal(seasonOne.publicKey.toBase58());
+
+      // A registered player in season 1
+      const name = utils.uniqueUsername();
+      await program.methods.buyPremarket(new anchor.BN(1e9), null)
+        .accounts({ payer: randomAccount2.publicKey, player: randomAccount2.publicKey, authority: seasonOne.publicKey, referrer: null })
+        .signers([randomAccount2])
+        .rpc();
+      await program.methods.register(name)
+        .accounts({ player: randomAccount2.publicKey, authority: seasonOne.publicKey })
+        .signers([randomAccount2])
+        .rpc();
+
+      const startSeasonTwo = () => program.methods
+        .startSeason(premarketEnd, new anchor.BN(5), null, null, null, { treasury: true, bonuses: true })
+        .accounts({ owner: owner.publicKey, authority: seasonTwo.publicKey, previousAuthority: seasonOne.publicKey })
+        .signers([seasonTwo, owner])
+        .rpc();
+
+      // Season 2 waits for season 1 to end
+      await utils.shouldError(startSeasonTwo(), "Game is not over");
+
+      await program.methods.endPremarket()
+        .accounts({ authority: seasonOne.publicKey })
+        .signers([seasonOne])
+        .rpc();
+      await program.methods.setMarket(new anchor.BN(10).pow(new anchor.BN(34)))
+        .accounts({ authority: seasonOne.publicKey })
+        .signers([seasonOne])
+        .rpc();
+      await program.methods.finalizeGame()
+        .accounts({ caller: wallet.publicKey, authority: seasonOne.publicKey })
+        .rpc();
+      await startSeasonTwo();
+
+      const registryState = await program.account.gameRegistry.fetch(registry);
+      expect(registryState.season.toNumber()).to.equal(2);
+      expect(registryState.authority.toBase58()).to.equal(seasonTwo.publicKey.toBase58());
+
+      const gameTwo = await program.account.gameState.fetch(utils.findGameDataAcc(seasonTwo.publicKey));
+      expect(gameTwo.season.toNumber()).to.equal(2);
+      expect(gameTwo.previousAuthority.toBase58()).to.equal(seasonOne.publicKey.toBase58());
+      expect(gameTwo.dev1.toBase58()).to.equal(registryDevs[0].publicKey.toBase58());
+
+      // Leftover treasury of season 1 is swept into the season 2 pool
+      const gameOne = await program.account.gameState.fetch(utils.findGameDataAcc(seasonOne.publicKey));
+      expect((gameOne.sweepDestination as any).nextSeason.authority.toBase58()).to.equal(seasonTwo.publicKey.toBase58());
+
+      // The username stays with its owner in the profile registry
+      for (const player of [randomAccount, randomAccount2]) {
+        await program.methods.buyPremarket(new anchor.BN(1e7), null)
+          .accounts({ payer: player.publicKey, player: player.publicKey, authority: seasonTwo.publicKey, referrer: null })
+          .signers([player])
+          .rpc();
+      }
+      await utils.shouldError(program.methods.register(name)
+        .accounts({ player: randomAccount.publicKey, authority: seasonTwo.publicKey })
+        .signers([randomAccount])
+        .rpc(), "Username is taken");
+      await program.methods.register(name)
+        .accounts({ player: randomAccount2.publicKey, authority: seasonTwo.publicKey })
+        .signers([randomAccount2])
+        .rpc();
+
+      const [profile] = utils.findProfileAcc(randomAccount2.publicKey, program.programId);
+      const profileState = await program.account.playerProfile.fetch(profile);
+      expect(profileState.username).to.equal(name);
+      expect(profileState.seasonsPlayed.toNumber()).to.equal(2);
+
+      // Bonuses are brought over per player
+      await program.methods.carryOverPlayer()
+        .accounts({ player: randomAccount2.publicKey, authority: seasonTwo.publicKey, previousAuthority: seasonOne.publicKey })
+        .signers([randomAccount2])
+        .rpc();
+      const player = await program.account.playerState.fetch(utils.findPlayerDataAcc(randomAccount2.publicKey, seasonTwo.publicKey));
+      expect(player.registered).to.equal(true);
+    });
+  });
+
+  describe("Referral statistics", () => {
+    it("tracks referees, volume, fees and cashback per referrer", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount);
+
+      const sig = await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      await buyPremarket(randomAccount, amount, refAccount.publicKey);
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+
+      const [statsAccount] = utils.findReferralStatsAcc(refAccount.publicKey, authority.publicKey, program.programId);
+      const stats = await program.account.referralStats.fetch(statsAccount);
+      expect(stats.referrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+      expect(stats.referees.toNumber()).to.equal(2);
+      expect(stats.referredVolume.toNumber()).to.equal(amount.toNumber() * 3);
+      expect(stats.feesEarned.toNumber()).to.equal(amount.toNumber() * 3 * 0.04);
+      expect(stats.refereeCashback.toNumber()).to.equal(amount.toNumber() * 3 * 0.01);
+
+      // The buyer's cashback is kept apart from referral income
+      const player = await program.account.playerState.fetch(playerAccount);
+      expect(player.cashbackTotal.toNumber()).to.equal(amount.toNumber() * 2 * 0.01);
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "referralCredited");
+      expect(events).to.have.length(1);
+      expect((events[0].data as any).referrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+      expect((events[0].data as any).referralFee.toNumber()).to.equal(amount.toNumber() * 0.04);
+      expect((events[0].data as any).cashback.toNumber()).to.equal(amount.toNumber() * 0.01);
+    });
+  });
+
+  describe("Referral split", () => {
+    it("lets referrers give part of their fee back to referees", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount);
+
+      const setSplit = (cashback: number) => program.methods.setReferralSplit(new anchor.BN(cashback))
+        .accounts({ player: refAccount.publicKey, authority: authority.publicKey })
+        .signers([refAccount])
+        .rpc();
+
+      // The split stays within the 5% referral budget
+      await utils.shouldError(setSplit(6), "Referral split exceeds the referral budget");
+      await setSplit(3);
+
+      const sig = await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      const player = await program.account.playerState.fetch(playerAccount);
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.02);
+      expect(player.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.03);
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "referralCredited");
+      expect((events[0].data as any).referralFee.toNumber()).to.equal(amount.toNumber() * 0.02);
+      expect((events[0].data as any).cashback.toNumber()).to.equal(amount.toNumber() * 0.03);
+    });
+  });
+
+  describe("Referral tiers", () => {
+    it("pays the referrer's upline out of the referral fee", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount2);
+      await setupReferrer(randomAccount2);
+      await setupReferrer(refAccount);
+
+      // refAccount was referred by refAccount2, who was referred by randomAccount2
+      const setReferrer = async (player: Keypair, referrer: PublicKey) => program.methods.setReferrer()
+        .accounts(await setReferrerAccounts(player.publicKey, referrer))
+        .signers([player])
+        .rpc();
+      await program.methods.setReferrerPolicy({ free: {} })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      await setReferrer(refAccount2, randomAccount2.publicKey);
+      await setReferrer(refAccount, refAccount2.publicKey);
+
+      await utils.shouldError(program.methods.setReferralTiers([new anchor.BN(3), new anchor.BN(2)])
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Referral tiers exceed the referral fee");
+      await program.methods.setReferralTiers([new anchor.BN(2), new anchor.BN(1)])
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      const secondTierState = await utils.findPlayerDataAcc(refAccount2.publicKey, authority.publicKey);
+      const thirdTierState = await utils.findPlayerDataAcc(randomAccount2.publicKey, authority.publicKey);
+      const buy = (secondTierState: PublicKey | null, thirdTierState: PublicKey | null) => program.methods.buyPremarket(amount, null)
+        .accounts({
+          payer: wallet.publicKey,
+          player: wallet.publicKey,
+          authority: authority.publicKey,
+          referrer: refAccount.publicKey,
+          referrerState: refStateAccount,
+          referrerStats: utils.findReferralStatsAcc(refAccount.publicKey, authority.publicKey, program.programId)[0],
+          referrerProfile: utils.findProfileAcc(refAccount.publicKey, program.programId)[0],
+          secondTierState,
+          thirdTierState,
+        })
+        .rpc();
+
+      // Tier accounts must follow the referrer chain, and cannot be left out
+      await utils.shouldError(buy(null, null), "Invalid referrer");
+      await utils.shouldError(buy(null, thirdTierState), "Invalid referrer");
+      await utils.shouldError(buy(thirdTierState, secondTierState), "Invalid referrer");
+      await buy(secondTierState, thirdTierState);
+
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      const secondTier = await program.account.playerState.fetch(secondTierState);
+      const thirdTier = await program.account.playerState.fetch(thirdTierState);
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.01);
+      expect(secondTier.tierTotal.toNumber()).to.equal(amount.toNumber() * 0.02);
+      expect(thirdTier.tierTotal.toNumber()).to.equal(amount.toNumber() * 0.01);
+
+      // Tier income is withdrawable
+      await program.methods.userWithdraw()
+        .accounts({ player: refAccount2.publicKey, authority: authority.publicKey })
+        .signers([refAccount2])
+        .rpc();
+      const withdrawn = await program.account.playerState.fetch(secondTierState);
+      expect(withdrawn.tierWithdrawn.toNumber()).to.equal(amount.toNumber() * 0.02);
+    });
+
+    it("rejects fee schedules with a referral fee below the tiers", async () => {
+      await program.methods.setReferralTiers([new anchor.BN(2), new anchor.BN(1)])
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      const fees = {
+        devFee: new anchor.BN(4),
+        premarketFee: new anchor.BN(6),
+        referralFee: new anchor.BN(2),
+        referralCashback: new anchor.BN(1),
+        nftBonus: new anchor.BN(10),
+        testnetBonus: new anchor.BN(1),
+      };
+      await utils.shouldError(program.methods.setFeeSchedule(fees)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Referral tiers exceed the referral fee");
+    });
+
+    it("locks referral tiers after the premarket", async () => {
+      await advancePreMarket();
+      await utils.shouldError(program.methods.setReferralTiers([new anchor.BN(2), new anchor.BN(1)])
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Premarket is over");
+    });
+  });
+
+  describe("Referral guards", () => {
+    it("pays only referrers with a stake and caps income from one referee", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount);
+
+      await program.methods.setReferralGuards({ minReferrerSpend: new anchor.BN(2e7), refereeCap: new anchor.BN(5e6) })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      // The referrer only spent 0.01 SOL, so the buy goes through without paying them
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      const unstaked = await program.account.playerState.fetch(refStateAccount);
+      expect(unstaked.referralTotal.toNumber()).to.equal(0);
+      expect((await program.account.playerState.fetch(playerAccount)).referrerEarned.toNumber()).to.equal(0);
+      await buyPremarket(refAccount, new anchor.BN(1e7), NULL_KEY);
+
+      // 4% of two 0.1 SOL buys is capped at 0.005 SOL
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      const player = await program.account.playerState.fetch(playerAccount);
+      expect(refState.referralTotal.toNumber()).to.equal(5e6);
+      expect(player.referrerEarned.toNumber()).to.equal(5e6);
+    });
+
+    it("rejects fee schedules that leave the referee cap below its minimum", async () => {
+      // 4% of a minimum buy
+      await program.methods.setReferralGuards({ minReferrerSpend: new anchor.BN(0), refereeCap: new anchor.BN(4e5) })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      const fees = {
+        devFee: new anchor.BN(4),
+        premarketFee: new anchor.BN(6),
+        referralFee: new anchor.BN(8),
+        referralCashback: new anchor.BN(1),
+        nftBonus: new anchor.BN(10),
+        testnetBonus: new anchor.BN(1),
+      };
+      await utils.shouldError(program.methods.setFeeSchedule(fees)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Referral guards are out of range");
+    });
+
+    it("rejects out of range guards and locks them after the premarket", async () => {
+      const setGuards = (minReferrerSpend: number, refereeCap: number) => program.methods
+        .setReferralGuards({ minReferrerSpend: new anchor.BN(minReferrerSpend), refereeCap: new anchor.BN(refereeCap) })
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      await utils.shouldError(setGuards(0, 1), "Referral guards are out of range");
+      await utils.shouldError(setGuards(101e9, 0), "Referral guards are out of range");
+      await setGuards(1e7, 0);
+
+      await advancePreMarket();
+      await utils.shouldError(setGuards(2e7, 0), "Premarket is over");
+    });
+  });
+
+  describe("Referral window", () => {
+    it("stops paying the referrer once attribution expires", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount);
+
+      await utils.shouldError(program.methods.setReferralWindow(new anchor.BN(0))
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Referral window is out of range");
+      await program.methods.setReferralWindow(new anchor.BN(2))
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+      await new Promise(r => setTimeout(r, 3_000));
+      const poolBefore = (await program.account.gameState.fetch(utils.findGameDataAcc(authority.publicKey))).poolBalance.toNumber();
+      const sig = await buyPremarket(wallet.payer, amount, refAccount.publicKey);
+
+      // The referral budget goes to the pool instead
+      const refState = await program.account.playerState.fetch(refStateAccount);
+      const game = await program.account.gameState.fetch(utils.findGameDataAcc(authority.publicKey));
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.04);
+      expect(game.poolBalance.toNumber() - poolBefore).to.equal(amount.toNumber() * 0.96);
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "referralExpired");
+      expect(events).to.have.length(1);
+      expect((events[0].data as any).referrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+    });
+
+    it("locks the referral window after the premarket", async () => {
+      await advancePreMarket();
+      await utils.shouldError(program.methods.setReferralWindow(new anchor.BN(2))
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc(), "Premarket is over");
+    });
+  });
+
+  describe("Partial sells", () => {
+    it("sells part of the eggs and hatches the rest", async () => {
+      await advancePreMarket();
+      await buyShrimp(wallet.payer, new anchor.BN(1_000e8), NULL_KEY);
+      await new Promise(r => setTimeout(r, 2_000));
+
+      const accounts = { player: wallet.publicKey, authority: authority.publicKey, nftAsset: null };
+      await utils.shouldError(
+        program.methods.sellEggsPartial(new anchor.BN("340282366920938463463374607431768211455"), null, null).accounts(accounts).rpc(),
+        "Egg amount must be positive and at most the player's eggs",
+      );
+      await utils.shouldError(
+        program.methods.hatchAndSell(10_000, null, null).accounts(accounts).rpc(),
+        "Sell share must be between 0 and 10000 basis points, exclusive",
+      );
+
+      const before = await program.account.playerState.fetch(playerAccount);
+      const sig = await program.methods.hatchAndSell(5_000, null, null).accounts(accounts).rpc();
+
+      const after = await program.account.playerState.fetch(playerAccount);
+      expect(after.shrimp.gt(before.shrimp)).to.be.true;
+      expect(after.sellTotal.gt(before.sellTotal)).to.be.true;
+      expect(after.lastSell.toNumber()).to.equal(after.lastHatch.toNumber());
+
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const names = [...parser.parseLogs(tx!.meta!.logMessages!)].map(e => e.name);
+      expect(names).to.deep.equal(["sell", "hatch"]);
+    });
+  });
+
+  describe("Referrer changes", () => {
+    it("changes the referrer with set_referrer under the game's policy", async () => {
+      const amount = new anchor.BN(1e8);
+      await setupReferrer(refAccount);
+      await setupReferrer(refAccount2);
+
+      const setReferrer = async (referrer: PublicKey) => program.methods.setReferrer()
+        .accounts(await setReferrerAccounts(wallet.publicKey, referrer))
+        .rpc({ commitment: "confirmed" });
+      const setPolicy = (policy: any) => program.methods.setReferrerPolicy(policy)
+        .accounts({ authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      // A player can pick and change their referrer before buying
+      await setReferrer(refAccount.publicKey);
+      const sig = await setReferrer(refAccount2.publicKey);
+      const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const events = [...parser.parseLogs(tx!.meta!.logMessages!)].filter(e => e.name === "referrerSet");
+      expect(events).to.have.length(1);
+      expect((events[0].data as any).previousReferrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+      expect((events[0].data as any).referrer.toBase58()).to.equal(refAccount2.publicKey.toBase58());
+
+      // The current referrer's accounts cannot be left out to skip the referral fee
+      await utils.shouldError(program.methods.buyPremarket(amount, null)
+        .accounts({
+          payer: wallet.publicKey,
+          player: wallet.publicKey,
+          authority: authority.publicKey,
+          referrer: null,
+          referrerState: null,
+          referrerStats: null,
+          referrerProfile: null,
+        })
+        .rpc(), "Invalid referrer");
+
+      // Buys without a referrer account credit the current referrer
+      await program.methods.buyPremarket(amount, null)
+        .accounts({
+          payer: wallet.publicKey,
+          player: wallet.publicKey,
+          authority: authority.publicKey,
+          referrer: null,
+          referrerState: refStateAccount2,
+          referrerStats: utils.findReferralStatsAcc(refAccount2.publicKey, authority.publicKey, program.programId)[0],
+          referrerProfile: utils.findProfileAcc(refAccount2.publicKey, program.programId)[0],
+        })
+        .rpc();
+      const refState = await program.account.playerState.fetch(refStateAccount2);
+      expect(refState.referralTotal.toNumber()).to.equal(amount.toNumber() * 0.04);
+
+      // Locked after the first purchase, then changeable as the policy allows
+      await utils.shouldError(setReferrer(refAccount.publicKey), "Referrer cannot be changed yet");
+      await setPolicy({ after: { days: 1 } });
+      await utils.shouldError(setReferrer(refAccount.publicKey), "Referrer cannot be changed yet");
+      await setPolicy({ free: {} });
+      await setReferrer(refAccount.publicKey);
+
+      const player = await program.account.playerState.fetch(playerAccount);
+      expect(player.currentReferrer.toBase58()).to.equal(refAccount.publicKey.toBase58());
+
+      // Only the current referrer counts the player as a referee
+      const stats = (referrer: PublicKey) => program.account.referralStats.fetch(
+        utils.findReferralStatsAcc(referrer, authority.publicKey, program.programId)[0]);
+      expect((await stats(refAccount.publicKey)).referees.toNumber()).to.equal(1);
+      expect((await stats(refAccount2.publicKey)).referees.toNumber()).to.equal(0);
+    });
+  });
+
   /* ------------------------------------------------------------------ 9 */
   describe("Program guard", () => {
     it("can add additional programs to the program whitelist", async () => {
@@ -1302,7 +2371,7 @@ describe("Shrimp Farm", () => {
       const additionalComputeIx = anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 });
       const amount = new anchor.BN(1e8);
 
-      const tx = await program.methods.buyPremarket(amount)
+      const tx = await program.methods.buyPremarket(amount, null)
         .accounts({ payer: fundedAccount.publicKey, player: fundedAccount.publicKey, authority: authority.publicKey, referrer: NULL_KEY })
         .instruction();
 
@@ -1352,6 +2421,9 @@ describe("Shrimp Farm", () => {
           defaultPremarketEnd,
           new anchor.BN(5), // 5 s cooldown
           false,
+          null,
+          null,
+          null,
         )
         .accounts({ authority: newAuthority.publicKey, owner: badOwner.publicKey })
         .signers([newAuthority, badOwner])
@@ -1380,6 +2452,9 @@ describe("Shrimp Farm", () => {
             defaultPremarketEnd,
             new anchor.BN(5), // 5 s cooldown
             false,
+            null,
+            null,
+            null,
           )
           .accounts({ authority: newAuthority.publicKey, owner: owner.publicKey })
           .signers([newAuthority, owner])
@@ -1405,6 +2480,9 @@ describe("Shrimp Farm", () => {
             defaultPremarketEnd,
             new anchor.BN(5), // 5 s cooldown
             false,
+            null,
+            null,
+            null,
           )
           .accounts({ authority: newAuthority.publicKey, owner: owner.publicKey })
           .signers([newAuthority, owner])
@@ -38,14 +38,6 @@ const findPlayerDataAcc = (player: PublicKey, authority: PublicKey) => {
   return playerDataAcc;
 };
 
-const findContractDataAcc = (authority: PublicKey) => {
-  const [assetManager] = PublicKey.findProgramAddressSync(
-      [Buffer.from("contractdata"), authority.toBuffer()],
-      SHRIMP_PROGRAM_ID
-  );
-  return assetManager
-}
-
 const findGameDataAcc = (authority: PublicKey) => {
   const [assetManager] = PublicKey.findProgramAddressSync(
       [Buffer.from("shrimp"), authority.toBuffer()],
@@ -54,13 +46,13 @@ const findGameDataAcc = (authority: PublicKey) => {
   return assetManager
 }
 
-
-const findGameTreasuryAcc = (authority: PublicKey)=> {
-  const [assetManager] = PublicKey.findProgramAddressSync(
-      [Buffer.from("treasury"), authority.toBuffer()],
+// kind: "dev" | "premarket" | "payables" | "pool"
+const findVault = (kind: string, authority: PublicKey) => {
+  const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from(`${kind}_vault`), authority.toBuffer()],
       SHRIMP_PROGRAM_ID
   );
-  return assetManager
+  return vault
 }
 
 const findNftMintAuthority = (authority: PublicKey)=> {
@@ -71,21 +63,23 @@ const findNftMintAuthority = (authority: PublicKey)=> {
   return assetManager
 }
 
-const findUsernameToAddressAcc = (username: string, programId: PublicKey, authority: PublicKey): [PublicKey, number] => {
-  return PublicKey.findProgramAddressSync([Buffer.from("username_to_address"), Buffer.from(username), authority.toBuffer()], programId);
+// Usernames and profiles are global, shared by every game
+const findUsernameToAddressAcc = (username: string, programId: PublicKey): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync([Buffer.from("username_to_address"), Buffer.from(username)], programId);
 };
 
-const findAddressToUsernameAcc = (playerPubKey, programId, authority) => {
-  return PublicKey.findProgramAddressSync(
-    [
-      Buffer.from("address_to_username"), 
-      playerPubKey.toBuffer(),
-      authority.toBuffer()
-    ],
-    programId
-  )
+const findProfileAcc = (playerPubKey: PublicKey, programId: PublicKey): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync([Buffer.from("profile"), playerPubKey.toBuffer()], programId);
 };
 
+const findReferralStatsAcc = (referrer: PublicKey, authority: PublicKey, programId: PublicKey): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync([Buffer.from("referral_stats"), referrer.toBuffer(), authority.toBuffer()], programId);
+};
+
+// Usernames are claimed once across every test game, so each test uses a fresh one
+const uniqueUsername = () =>
+  Array.from({ length: 12 }, () => String.fromCharCode(97 + Math.floor(Math.random() * 26))).join("");
+
 const findPlayerDataAccWithDebug = (player: PublicKey, authority: PublicKey) => {
   console.log("Debug PDA derivation:");
   console.log("Player pubkey bytes:", [...player.toBuffer()]);
@@ -148,5 +142,5 @@ async function getBonusPercentFromTx(
 // Types
 
 export { TOKEN_METADATA_PROGRAM_ID,
-  findPlayerDataAcc, findGameDataAcc, findContractDataAcc, findGameTreasuryAcc, findNftMintAuthority, findUsernameToAddressAcc, findAddressToUsernameAcc, shouldError, shouldRevert, findPlayerDataAccWithDebug,
+  findPlayerDataAcc, findGameDataAcc, findVault, findNftMintAuthority, findUsernameToAddressAcc, findProfileAcc, findReferralStatsAcc, uniqueUsername, shouldError, shouldRevert, findPlayerDataAccWithDebug,
   getPDAPublicKey, getMetadata, getMasterEdition, getBonusPercentFromTx };